@@ -0,0 +1,594 @@
+use std::{
+    f64::consts::PI,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use rustfft::num_complex::Complex;
+use wave_stream::{
+    open_wav::OpenWav, read_wav_from_file_path, wave_reader::RandomAccessOpenWavReader,
+};
+
+use crate::{
+    discrete::{discrete_channels, has_channel_mask},
+    fade::Fade,
+    hilbert::analytic_signal,
+    matrix::MatrixEncoder,
+    panning::pan,
+    position::Position,
+    signal::Signal,
+    tone_generator::{seconds_to_samples, Segment},
+};
+
+// Implied azimuths are searched for in steps of this many degrees
+const AZIMUTH_RESOLUTION: f32 = 0.5;
+
+// A decoded channel is part of a position when panning feeds it at least this gain
+const INTENDED_GAIN: f32 = 0.01;
+
+// The first tone in a decoded wav starts at about the first sample this far below the peak
+const ONSET_THRESHOLD: f32 = 0.01;
+
+// How many samples either side of the rough latency, in addition to the fade, are searched for the
+// exact latency
+const LATENCY_SEARCH: usize = 256;
+
+// Mismatches closer than this are considered the same, so rounding errors don't break ties
+const MISMATCH_TOLERANCE: f64 = 0.000_000_001;
+
+/// Returns true if a wav file has two channels, like a generated file, or false if it has more, like
+/// a decoded file
+pub fn is_stereo(path: &Path) -> Result<bool> {
+    Ok(read_wav_from_file_path(path)?.num_channels() == 2)
+}
+
+/// A stereo (left total, right total) wav file, read into memory
+pub struct StereoWav {
+    pub sample_rate: u32,
+    pub left_total: Vec<f32>,
+    pub right_total: Vec<f32>,
+}
+
+impl StereoWav {
+    /// Reads a stereo wav file
+    pub fn read(path: &Path) -> Result<StereoWav> {
+        let open_wav = read_wav_from_file_path(path)?;
+        if open_wav.num_channels() != 2 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Only stereo wavs can be analyzed, this has {} channels",
+                    open_wav.num_channels()
+                ),
+            ));
+        }
+
+        let sample_rate = open_wav.sample_rate();
+        let len_samples = open_wav.len_samples();
+        let mut reader = open_wav.get_random_access_f32_reader()?;
+
+        let mut left_total = Vec::with_capacity(len_samples);
+        let mut right_total = Vec::with_capacity(len_samples);
+        for sample_ctr in 0..len_samples {
+            let samples_by_channel = reader.read_sample(sample_ctr)?;
+            left_total.push(samples_by_channel.front_left.unwrap_or(0.0));
+            right_total.push(samples_by_channel.front_right.unwrap_or(0.0));
+        }
+
+        Ok(StereoWav {
+            sample_rate,
+            left_total,
+            right_total,
+        })
+    }
+
+    /// Measures each tone in each segment. Only tones can be measured: Segments with sweeps or noise
+    /// have no measurements
+    pub fn analyze(&self, segments: &[Segment]) -> Result<Vec<SegmentAnalysis>> {
+        let mut analyses = Vec::new();
+        for segment in segments.iter() {
+            let end = segment.start + segment.length;
+            if end > self.left_total.len() {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "The wav has {} samples, but {} ends at sample {}. Do the tone and silence lengths match the ones it was generated with?",
+                        self.left_total.len(),
+                        segment.tone.placement.name(),
+                        end
+                    ),
+                ));
+            }
+
+            let frequencies = match &segment.signal {
+                Signal::Tone { frequencies } => frequencies.clone(),
+                _ => Vec::new(),
+            };
+
+            let tones = frequencies
+                .into_iter()
+                .map(|frequency| ToneAnalysis {
+                    frequency,
+                    left_total: measure_tone(
+                        &self.left_total[segment.start..end],
+                        self.sample_rate,
+                        frequency,
+                    ),
+                    right_total: measure_tone(
+                        &self.right_total[segment.start..end],
+                        self.sample_rate,
+                        frequency,
+                    ),
+                })
+                .collect();
+
+            analyses.push(SegmentAnalysis {
+                segment: segment.clone(),
+                tones,
+            });
+        }
+
+        Ok(analyses)
+    }
+}
+
+/// The measurements of one segment of a file
+pub struct SegmentAnalysis {
+    pub segment: Segment,
+    /// One measurement for each of the segment's frequencies
+    pub tones: Vec<ToneAnalysis>,
+}
+
+/// The amplitude and phase of a tone in left total and right total
+pub struct ToneAnalysis {
+    pub frequency: f32,
+    pub left_total: Complex<f32>,
+    pub right_total: Complex<f32>,
+}
+
+impl ToneAnalysis {
+    /// Left total's level relative to right total's, in dB
+    pub fn level_difference(&self) -> f32 {
+        20.0 * (self.left_total.norm() / self.right_total.norm()).log10()
+    }
+
+    /// Left total's phase relative to right total's, in degrees from -180 to 180
+    pub fn phase_difference(&self) -> f32 {
+        // Adding 0 turns -0 into 0
+        (self.left_total * self.right_total.conj())
+            .arg()
+            .to_degrees()
+            + 0.0
+    }
+
+    /// The azimuth, in degrees, whose gains in the matrix are closest to the measured tone. Only the
+    /// relationship between left total and right total matters, not the overall level or phase.
+    /// Some matrixes encode more than one azimuth the same way; the one closest to center is used
+    pub fn implied_azimuth(&self, encoder: &dyn MatrixEncoder) -> f32 {
+        let steps = (180.0 / AZIMUTH_RESOLUTION) as usize;
+
+        // Searches outward from center, so that ties go to the azimuth closest to center
+        let azimuths = (0..=steps).flat_map(|step| {
+            let azimuth = AZIMUTH_RESOLUTION * step as f32;
+            [azimuth, -azimuth]
+        });
+
+        // Nearby azimuths can have very similar gains, so the mismatch is calculated at a higher
+        // precision
+        let measured_left_total = to_f64(self.left_total);
+        let measured_right_total = to_f64(self.right_total);
+        let measured_power = measured_left_total.norm_sqr() + measured_right_total.norm_sqr();
+
+        let mut best_azimuth = 0.0;
+        let mut best_mismatch = f64::MAX;
+        for azimuth in azimuths {
+            let (left_total, right_total) = encoder.encode_azimuth(azimuth);
+            let left_total = to_f64(left_total);
+            let right_total = to_f64(right_total);
+
+            // 1 - the squared cosine of the angle between the measured and encoded gains, which is 0
+            // when they only differ by a complex scale
+            let inner =
+                measured_left_total * left_total.conj() + measured_right_total * right_total.conj();
+            let encoded_power = left_total.norm_sqr() + right_total.norm_sqr();
+            let mismatch = 1.0 - inner.norm_sqr() / (measured_power * encoded_power);
+
+            if mismatch < best_mismatch - MISMATCH_TOLERANCE {
+                best_mismatch = mismatch;
+                best_azimuth = azimuth;
+            }
+        }
+
+        best_azimuth
+    }
+}
+
+/// A multichannel wav file that a matrix decoder wrote, read into memory. Channels are mapped to
+/// positions like `discrete::discrete_channels`, and LFE is ignored
+pub struct DecodedWav {
+    pub sample_rate: u32,
+    pub channels: Vec<DecodedChannel>,
+}
+
+/// A channel in a decoded wav file
+pub struct DecodedChannel {
+    /// The channel's name, such as "back left"
+    pub name: &'static str,
+    pub position: Position,
+    pub samples: Vec<f32>,
+}
+
+impl DecodedWav {
+    /// Reads a decoded wav file
+    pub fn read(path: &Path) -> Result<DecodedWav> {
+        let open_wav = read_wav_from_file_path(path)?;
+        let sample_rate = open_wav.sample_rate();
+        let len_samples = open_wav.len_samples();
+        let discrete_channels = discrete_channels(open_wav.channels(), has_channel_mask(path)?)?;
+        let mut reader = open_wav.get_random_access_f32_reader()?;
+
+        let mut channels: Vec<DecodedChannel> = discrete_channels
+            .iter()
+            .map(|channel| DecodedChannel {
+                name: channel.name,
+                position: channel.position,
+                samples: Vec::with_capacity(len_samples),
+            })
+            .collect();
+
+        for sample_ctr in 0..len_samples {
+            let samples_by_channel = reader.read_sample(sample_ctr)?;
+            for (channel, discrete_channel) in channels.iter_mut().zip(discrete_channels.iter()) {
+                channel
+                    .samples
+                    .push(discrete_channel.sample(&samples_by_channel).unwrap_or(0.0));
+            }
+        }
+
+        Ok(DecodedWav {
+            sample_rate,
+            channels,
+        })
+    }
+
+    /// How many samples later the first segment starts than expected. Decoders that process audio in
+    /// windows delay their output. The delay is roughly where the first sample is louder than -40 dB
+    /// relative to the loudest sample, but a fade-in crosses -40 dB late, so the delay is refined by
+    /// cross-correlating the first segment, faded in with `fade`, around that point
+    pub fn latency(&self, segments: &[Segment], fade: Fade) -> usize {
+        let first_segment = match segments.first() {
+            Some(segment) => segment,
+            None => return 0,
+        };
+
+        let peak = self
+            .channels
+            .iter()
+            .flat_map(|channel| channel.samples.iter())
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+
+        let onset = self
+            .channels
+            .iter()
+            .filter_map(|channel| {
+                channel
+                    .samples
+                    .iter()
+                    .position(|sample| sample.abs() > peak * ONSET_THRESHOLD)
+            })
+            .min();
+        let rough_latency = match onset {
+            Some(onset) => onset.saturating_sub(first_segment.start),
+            None => return 0,
+        };
+
+        // Decoders shift the phase of their outputs, so analytic signals are correlated, and only
+        // the correlation's magnitude is compared
+        let expected: Vec<f32> = first_segment
+            .signal
+            .render(self.sample_rate, first_segment.length)
+            .iter()
+            .zip(fade.envelope(self.sample_rate, first_segment.length))
+            .map(|(sample, gain)| sample.re * gain)
+            .collect();
+        let expected = analytic_signal(&expected);
+        let channels: Vec<Vec<Complex<f32>>> = self
+            .channels
+            .iter()
+            .map(|channel| analytic_signal(&channel.samples))
+            .collect();
+
+        let search = seconds_to_samples(self.sample_rate, fade.duration) + LATENCY_SEARCH;
+        (rough_latency.saturating_sub(search)..=rough_latency + search)
+            .map(|latency| {
+                let start = first_segment.start + latency;
+                let correlation: f64 = channels
+                    .iter()
+                    .map(|channel| {
+                        channel
+                            .iter()
+                            .skip(start)
+                            .zip(expected.iter())
+                            .map(|(decoded, expected)| to_f64(decoded * expected.conj()))
+                            .sum::<Complex<f64>>()
+                            .norm_sqr()
+                    })
+                    .sum();
+
+                (latency, correlation)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(latency, _)| latency)
+            .unwrap_or(rough_latency)
+    }
+
+    /// Measures each channel's level in each segment, after delaying the segments by `latency`
+    /// samples
+    pub fn analyze(
+        &self,
+        segments: &[Segment],
+        latency: usize,
+    ) -> Result<Vec<DecodedSegmentAnalysis>> {
+        let len_samples = self
+            .channels
+            .first()
+            .map(|channel| channel.samples.len())
+            .unwrap_or(0);
+        let azimuths: Vec<f32> = self
+            .channels
+            .iter()
+            .map(|channel| channel.position.azimuth())
+            .collect();
+
+        let mut analyses = Vec::new();
+        for segment in segments.iter() {
+            let start = segment.start + latency;
+            let end = start + segment.length;
+            if end > len_samples {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "The wav has {} samples, but {} ends at sample {}. Do the tone and silence lengths match the ones it was generated with?",
+                        len_samples,
+                        segment.tone.placement.name(),
+                        end
+                    ),
+                ));
+            }
+
+            let levels = self
+                .channels
+                .iter()
+                .map(|channel| {
+                    measure_level(
+                        &channel.samples[start..end],
+                        self.sample_rate,
+                        &segment.signal,
+                    )
+                })
+                .collect();
+
+            let intended = pan(segment.tone.placement.azimuth(), &azimuths)
+                .iter()
+                .map(|gain| *gain >= INTENDED_GAIN)
+                .collect();
+
+            analyses.push(DecodedSegmentAnalysis {
+                segment: segment.clone(),
+                levels,
+                intended,
+            });
+        }
+
+        Ok(analyses)
+    }
+}
+
+/// The levels of each channel in one segment of a decoded file
+pub struct DecodedSegmentAnalysis {
+    pub segment: Segment,
+    /// Each channel's level, in dB relative to a full-scale sine wave
+    pub levels: Vec<f32>,
+    /// Which channels the segment's position should be decoded to: The one or two channels that the
+    /// position is panned between
+    pub intended: Vec<bool>,
+}
+
+impl DecodedSegmentAnalysis {
+    /// The combined level of the intended channels, relative to the loudest other channel, in dB.
+    /// This is negative when the position is decoded to the wrong channel
+    pub fn separation(&self) -> f32 {
+        let mut intended_power = 0.0;
+        let mut loudest_other = f32::NEG_INFINITY;
+        for (level, intended) in self.levels.iter().zip(self.intended.iter()) {
+            if *intended {
+                intended_power += 10.0f32.powf(level / 10.0);
+            } else {
+                loudest_other = loudest_other.max(*level);
+            }
+        }
+
+        10.0 * intended_power.log10() - loudest_other
+    }
+}
+
+/// Measures a signal's level, in dB relative to a full-scale sine wave. Tones are measured at their
+/// frequencies, so noise and other channels' leakage are ignored; other signals are measured by
+/// their RMS level
+pub fn measure_level(samples: &[f32], sample_rate: u32, signal: &Signal) -> f32 {
+    match signal {
+        Signal::Tone { frequencies } => {
+            let power: f32 = frequencies
+                .iter()
+                .map(|frequency| measure_tone(samples, sample_rate, *frequency).norm_sqr())
+                .sum();
+
+            10.0 * power.log10()
+        }
+        _ => {
+            let mean_square = samples
+                .iter()
+                .map(|sample| (*sample as f64) * (*sample as f64))
+                .sum::<f64>()
+                / samples.len() as f64;
+
+            // A full-scale sine's mean square is 1/2
+            (10.0 * (mean_square * 2.0).log10()) as f32
+        }
+    }
+}
+
+/// Measures a tone's amplitude and phase with a Hann-windowed DFT at the tone's frequency. The
+/// result's amplitude is the tone's peak amplitude, and its phase is relative to the first sample
+pub fn measure_tone(samples: &[f32], sample_rate: u32, frequency: f32) -> Complex<f32> {
+    let length = samples.len();
+    let mut sum = Complex::new(0.0f64, 0.0f64);
+    let mut window_sum = 0.0f64;
+    for (sample_ctr, sample) in samples.iter().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * PI * sample_ctr as f64 / length as f64).cos();
+        let phase = -2.0 * PI * frequency as f64 * sample_ctr as f64 / sample_rate as f64;
+
+        sum += Complex::from_polar(*sample as f64 * window, phase);
+        window_sum += window;
+    }
+
+    let amplitude = sum * 2.0 / window_sum;
+    Complex::new(amplitude.re as f32, amplitude.im as f32)
+}
+
+fn to_f64(value: Complex<f32>) -> Complex<f64> {
+    Complex::new(value.re as f64, value.im as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use wave_stream::{samples_by_channel::SamplesByChannel, wave_header::Channels};
+
+    use super::*;
+    use crate::{
+        fade::FadeShape,
+        matrix::SqMatrix,
+        sequence::ToneSequence,
+        writer::{OutputFormat, WavWriter},
+        ToneGenerator,
+    };
+
+    // A decoded wav with one channel, where the first segment is delayed by `latency` samples
+    fn delayed_wav(segments: &[Segment], fade: Fade, latency: usize) -> DecodedWav {
+        let segment = &segments[0];
+        let mut written = vec![0.0; segment.start + latency];
+        let rendered = segment.signal.render(44100, segment.length);
+        for (sample, gain) in rendered.iter().zip(fade.envelope(44100, segment.length)) {
+            written.push(sample.re * gain);
+        }
+        written.extend(vec![0.0; 1000]);
+
+        // Shifted by 90°, like a passive decoder's rear channels
+        let samples = analytic_signal(&written)
+            .iter()
+            .map(|sample| (sample * Complex::new(0.0, -0.5)).re)
+            .collect();
+
+        DecodedWav {
+            sample_rate: 44100,
+            channels: vec![DecodedChannel {
+                name: "front left",
+                position: Position::LeftFront,
+                samples,
+            }],
+        }
+    }
+
+    #[test]
+    fn latency_is_corrected_for_the_fade() {
+        let segments = ToneGenerator::with_durations(44100, 882.0, 0.25, 0.025)
+            .segments(&ToneSequence::for_matrix(&SqMatrix));
+
+        let fades = [
+            Fade::none(),
+            Fade::default(),
+            Fade {
+                shape: FadeShape::Blackman,
+                duration: 0.02,
+            },
+        ];
+        for fade in fades {
+            for latency in [0, 137] {
+                let wav = delayed_wav(&segments, fade, latency);
+                assert_eq!(wav.latency(&segments, fade), latency, "{:?}", fade);
+            }
+        }
+    }
+
+    // Writes a wav with the channels and one sample, which differs in each channel, and reads it back
+    fn read_decoded(file_name: &str, channels: Channels) -> DecodedWav {
+        let path = std::env::temp_dir().join(file_name);
+        let mut writer =
+            WavWriter::create(&path, channels, 44100, OutputFormat::default()).unwrap();
+        writer
+            .write_samples(
+                0,
+                SamplesByChannel::new()
+                    .front_left(1.0)
+                    .front_right(2.0)
+                    .front_center(3.0)
+                    .low_frequency(4.0)
+                    .side_left(5.0)
+                    .side_right(6.0),
+            )
+            .unwrap();
+        writer.finish().unwrap();
+
+        let wav = DecodedWav::read(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        wav
+    }
+
+    fn positions(wav: &DecodedWav) -> Vec<(Position, Vec<f32>)> {
+        wav.channels
+            .iter()
+            .map(|channel| (channel.position, channel.samples.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_quad_with_side_channels() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .side_left()
+            .side_right();
+        let wav = read_decoded("soft_matrix_test_tones_quad_side.wav", channels);
+
+        assert_eq!(
+            positions(&wav),
+            [
+                (Position::LeftFront, vec![1.0]),
+                (Position::RightFront, vec![2.0]),
+                (Position::LeftRear, vec![5.0]),
+                (Position::RightRear, vec![6.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_three_one() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .front_center()
+            .low_frequency();
+        let wav = read_decoded("soft_matrix_test_tones_three_one.wav", channels);
+
+        assert_eq!(
+            positions(&wav),
+            [
+                (Position::LeftFront, vec![1.0]),
+                (Position::RightFront, vec![2.0]),
+                (Position::Center, vec![3.0]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,507 @@
+use std::{
+    fs::File,
+    io::{copy, sink, Error, ErrorKind, Read, Result},
+    path::Path,
+};
+
+use wave_stream::{
+    open_wav::OpenWav, read_wav_from_file_path, samples_by_channel::SamplesByChannel,
+    wave_header::Channels, wave_reader::RandomAccessOpenWavReader,
+};
+
+use crate::{
+    hilbert::{HilbertTransformer, DEFAULT_TAPS},
+    matrix::{apply_gains, Gains, MatrixDecoder, MatrixEncoder},
+    position::Position,
+    writer::{OutputFormat, WavWriter},
+};
+
+// A wav without a channel mask only has a channel count, which wave_stream assigns in speaker order:
+// Four channels become front left, front right, front center, and LFE, just like a 3.1 wav's mask
+const UNMASKED_QUAD: u32 = 0xF;
+
+// The fmt chunk's format tag for WAVE_FORMAT_EXTENSIBLE, the only format with a channel mask
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+// Sets a channel in a wav file's header, and its sample
+type SpeakerChannel = (
+    fn(Channels) -> Channels,
+    fn(SamplesByChannel<f32>, f32) -> SamplesByChannel<f32>,
+);
+
+/// A channel in a discrete wav file, and the position that it's encoded at
+pub struct DiscreteChannel {
+    /// The channel's name, such as "back left"
+    pub name: &'static str,
+    pub position: Position,
+    sample: fn(&SamplesByChannel<f32>) -> Option<f32>,
+}
+
+impl DiscreteChannel {
+    /// The channel's sample, from all of the samples in a frame
+    pub(crate) fn sample(&self, samples_by_channel: &SamplesByChannel<f32>) -> Option<f32> {
+        (self.sample)(samples_by_channel)
+    }
+
+    fn new(
+        name: &'static str,
+        position: Position,
+        sample: fn(&SamplesByChannel<f32>) -> Option<f32>,
+    ) -> DiscreteChannel {
+        DiscreteChannel {
+            name,
+            position,
+            sample,
+        }
+    }
+}
+
+/// Maps the channels in a discrete wav file to positions. Back left and back right are the rears in
+/// a quad file, and the surrounds in a 5.1 file. Side left and side right are the rears in a quad
+/// file without back channels, the surrounds in a 5.1 file, or the middles when the file also has
+/// back channels. Four channels without a channel mask (see `has_channel_mask`) are treated as
+/// quad: left front, right front, left rear, right rear. LFE is not encoded
+pub fn discrete_channels(
+    channels: &Channels,
+    has_channel_mask: bool,
+) -> Result<Vec<DiscreteChannel>> {
+    if !has_channel_mask && channels.channel_mask() == UNMASKED_QUAD {
+        return Ok(vec![
+            DiscreteChannel::new("front left", Position::LeftFront, |s| s.front_left),
+            DiscreteChannel::new("front right", Position::RightFront, |s| s.front_right),
+            DiscreteChannel::new("back left", Position::LeftRear, |s| s.front_center),
+            DiscreteChannel::new("back right", Position::RightRear, |s| s.low_frequency),
+        ]);
+    }
+
+    let unsupported = [
+        (channels.front_left_of_center, "front left of center"),
+        (channels.front_right_of_center, "front right of center"),
+        (channels.top_center, "top center"),
+        (channels.top_front_left, "top front left"),
+        (channels.top_front_center, "top front center"),
+        (channels.top_front_right, "top front right"),
+        (channels.top_back_left, "top back left"),
+        (channels.top_back_center, "top back center"),
+        (channels.top_back_right, "top back right"),
+    ];
+    for (present, name) in unsupported {
+        if present {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("The {} channel can not be encoded", name),
+            ));
+        }
+    }
+
+    let has_sides = channels.side_left || channels.side_right;
+    let (back_left, back_right) = if channels.front_center && !has_sides {
+        (Position::LeftSurround, Position::RightSurround)
+    } else {
+        (Position::LeftRear, Position::RightRear)
+    };
+    let has_backs = channels.back_left || channels.back_right;
+    let (side_left, side_right) = if has_backs {
+        (Position::LeftMiddle, Position::RightMiddle)
+    } else if !channels.front_center {
+        (Position::LeftRear, Position::RightRear)
+    } else {
+        (Position::LeftSurround, Position::RightSurround)
+    };
+
+    let mut discrete_channels = Vec::new();
+    if channels.front_left {
+        discrete_channels.push(DiscreteChannel::new(
+            "front left",
+            Position::LeftFront,
+            |s| s.front_left,
+        ));
+    }
+    if channels.front_right {
+        discrete_channels.push(DiscreteChannel::new(
+            "front right",
+            Position::RightFront,
+            |s| s.front_right,
+        ));
+    }
+    if channels.front_center {
+        discrete_channels.push(DiscreteChannel::new(
+            "front center",
+            Position::Center,
+            |s| s.front_center,
+        ));
+    }
+    if channels.back_left {
+        discrete_channels.push(DiscreteChannel::new("back left", back_left, |s| {
+            s.back_left
+        }));
+    }
+    if channels.back_right {
+        discrete_channels.push(DiscreteChannel::new("back right", back_right, |s| {
+            s.back_right
+        }));
+    }
+    if channels.back_center {
+        discrete_channels.push(DiscreteChannel::new(
+            "back center",
+            Position::RearCenter,
+            |s| s.back_center,
+        ));
+    }
+    if channels.side_left {
+        discrete_channels.push(DiscreteChannel::new("side left", side_left, |s| {
+            s.side_left
+        }));
+    }
+    if channels.side_right {
+        discrete_channels.push(DiscreteChannel::new("side right", side_right, |s| {
+            s.side_right
+        }));
+    }
+
+    if discrete_channels.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "The wav has no channels that can be encoded",
+        ));
+    }
+
+    Ok(discrete_channels)
+}
+
+/// Whether a wav file's header has a channel mask. wave_stream gives unmasked files channels in
+/// speaker order, so this is the only way to tell an unmasked quad file from a 3.1 file
+pub fn has_channel_mask(path: &Path) -> Result<bool> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 12];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(Error::new(ErrorKind::InvalidData, "Not a wav file"));
+    }
+
+    loop {
+        let mut chunk_header = [0; 8];
+        file.read_exact(&mut chunk_header)?;
+        let chunk_size = u32::from_le_bytes([
+            chunk_header[4],
+            chunk_header[5],
+            chunk_header[6],
+            chunk_header[7],
+        ]);
+
+        if &chunk_header[0..4] == b"fmt " {
+            let mut format_tag = [0; 2];
+            file.read_exact(&mut format_tag)?;
+            return Ok(u16::from_le_bytes(format_tag) == FORMAT_EXTENSIBLE);
+        }
+
+        // Chunks are padded to an even size
+        let padded_size = chunk_size as u64 + chunk_size as u64 % 2;
+        if copy(&mut (&mut file).take(padded_size), &mut sink())? != padded_size {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "The wav has no fmt chunk",
+            ));
+        }
+    }
+}
+
+/// Encodes a discrete quad or 5.1 wav file into a stereo (left total, right total) wav file with
+/// the given matrix. Each channel is shifted with a `HilbertTransformer`, so any material can be
+/// encoded, and the output is aligned with the input. The output is at the input's sample rate, and
+/// is not normalized: Loud material can exceed full scale, which clips integer formats. Returns how
+/// the input's channels were encoded
+pub fn encode_wav(
+    input: &Path,
+    output: &Path,
+    encoder: &dyn MatrixEncoder,
+    output_format: OutputFormat,
+) -> Result<Vec<DiscreteChannel>> {
+    let open_wav = read_wav_from_file_path(input)?;
+    let sample_rate = open_wav.sample_rate();
+    let len_samples = open_wav.len_samples();
+    let discrete_channels = discrete_channels(open_wav.channels(), has_channel_mask(input)?)?;
+    let mut reader = open_wav.get_random_access_f32_reader()?;
+
+    let mut writer = WavWriter::create(
+        output,
+        Channels::new().front_left().front_right(),
+        sample_rate,
+        output_format,
+    )?;
+
+    let mut channels: Vec<(&DiscreteChannel, Gains, HilbertTransformer)> = discrete_channels
+        .iter()
+        .map(|channel| {
+            (
+                channel,
+                encoder.encode_position(channel.position),
+                HilbertTransformer::new(DEFAULT_TAPS),
+            )
+        })
+        .collect();
+
+    // The transformers delay their output, so they're fed silence after the end of the input
+    let latency = HilbertTransformer::new(DEFAULT_TAPS).latency();
+    for sample_ctr in 0..len_samples + latency {
+        let samples_by_channel = if sample_ctr < len_samples {
+            Some(reader.read_sample(sample_ctr)?)
+        } else {
+            None
+        };
+
+        let mut left_total = 0.0;
+        let mut right_total = 0.0;
+        for (channel, gains, hilbert_transformer) in channels.iter_mut() {
+            let sample = samples_by_channel
+                .as_ref()
+                .and_then(|samples_by_channel| channel.sample(samples_by_channel))
+                .unwrap_or(0.0);
+
+            let (channel_left_total, channel_right_total) =
+                apply_gains(*gains, hilbert_transformer.process(sample));
+            left_total += channel_left_total;
+            right_total += channel_right_total;
+        }
+
+        if sample_ctr >= latency {
+            writer.write_samples(
+                sample_ctr - latency,
+                SamplesByChannel::new()
+                    .front_left(left_total)
+                    .front_right(right_total),
+            )?;
+        }
+    }
+
+    writer.finish()?;
+
+    Ok(discrete_channels)
+}
+
+/// Decodes a stereo (left total, right total) wav file into a discrete wav file, with a channel for
+/// each of the decoder's positions. Each channel is shifted with a `HilbertTransformer`, and the
+/// output is aligned with the input. The output is at the input's sample rate
+pub fn decode_wav(
+    input: &Path,
+    output: &Path,
+    decoder: &dyn MatrixDecoder,
+    output_format: OutputFormat,
+) -> Result<()> {
+    let open_wav = read_wav_from_file_path(input)?;
+    if open_wav.num_channels() != 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Only stereo wavs can be decoded, this has {} channels",
+                open_wav.num_channels()
+            ),
+        ));
+    }
+
+    let sample_rate = open_wav.sample_rate();
+    let len_samples = open_wav.len_samples();
+    let mut reader = open_wav.get_random_access_f32_reader()?;
+
+    let speaker_channels = decoder
+        .positions()
+        .into_iter()
+        .map(speaker_channel)
+        .collect::<Result<Vec<SpeakerChannel>>>()?;
+
+    let channels = speaker_channels
+        .iter()
+        .fold(Channels::new(), |channels, (set_channel, _)| {
+            set_channel(channels)
+        });
+    let mut writer = WavWriter::create(output, channels, sample_rate, output_format)?;
+
+    let mut left_total_transformer = HilbertTransformer::new(DEFAULT_TAPS);
+    let mut right_total_transformer = HilbertTransformer::new(DEFAULT_TAPS);
+
+    // The transformers delay their output, so they're fed silence after the end of the input
+    let latency = left_total_transformer.latency();
+    for sample_ctr in 0..len_samples + latency {
+        let (left_total, right_total) = if sample_ctr < len_samples {
+            let samples_by_channel = reader.read_sample(sample_ctr)?;
+            (
+                samples_by_channel.front_left.unwrap_or(0.0),
+                samples_by_channel.front_right.unwrap_or(0.0),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        let decoded = decoder.decode(
+            left_total_transformer.process(left_total),
+            right_total_transformer.process(right_total),
+        );
+
+        if sample_ctr >= latency {
+            let samples_by_channel = speaker_channels.iter().zip(decoded).fold(
+                SamplesByChannel::new(),
+                |samples_by_channel, ((_, set_sample), sample)| {
+                    set_sample(samples_by_channel, sample.re)
+                },
+            );
+
+            writer.write_samples(sample_ctr - latency, samples_by_channel)?;
+        }
+    }
+
+    writer.finish()
+}
+
+// The wav channel that a decoded position is written to
+fn speaker_channel(position: Position) -> Result<SpeakerChannel> {
+    match position {
+        Position::LeftFront => Ok((Channels::front_left, SamplesByChannel::front_left)),
+        Position::RightFront => Ok((Channels::front_right, SamplesByChannel::front_right)),
+        Position::Center => Ok((Channels::front_center, SamplesByChannel::front_center)),
+        Position::LeftRear => Ok((Channels::back_left, SamplesByChannel::back_left)),
+        Position::RightRear => Ok((Channels::back_right, SamplesByChannel::back_right)),
+        Position::RearCenter => Ok((Channels::back_center, SamplesByChannel::back_center)),
+        Position::LeftMiddle => Ok((Channels::side_left, SamplesByChannel::side_left)),
+        Position::RightMiddle => Ok((Channels::side_right, SamplesByChannel::side_right)),
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Can not decode to {}", position.name()),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn positions(channels: Channels, has_channel_mask: bool) -> Vec<(&'static str, Position)> {
+        discrete_channels(&channels, has_channel_mask)
+            .unwrap()
+            .iter()
+            .map(|channel| (channel.name, channel.position))
+            .collect()
+    }
+
+    #[test]
+    fn unmasked_four_channels_are_quad() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .front_center()
+            .low_frequency();
+
+        assert_eq!(
+            positions(channels, false),
+            [
+                ("front left", Position::LeftFront),
+                ("front right", Position::RightFront),
+                ("back left", Position::LeftRear),
+                ("back right", Position::RightRear),
+            ]
+        );
+    }
+
+    #[test]
+    fn masked_three_one_is_not_quad() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .front_center()
+            .low_frequency();
+
+        assert_eq!(
+            positions(channels, true),
+            [
+                ("front left", Position::LeftFront),
+                ("front right", Position::RightFront),
+                ("front center", Position::Center),
+            ]
+        );
+    }
+
+    #[test]
+    fn sides_without_center_or_backs_are_rears() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .side_left()
+            .side_right();
+
+        assert_eq!(
+            positions(channels, true),
+            [
+                ("front left", Position::LeftFront),
+                ("front right", Position::RightFront),
+                ("side left", Position::LeftRear),
+                ("side right", Position::RightRear),
+            ]
+        );
+    }
+
+    #[test]
+    fn five_one_sides_are_surrounds() {
+        let channels = Channels::new()
+            .front_left()
+            .front_right()
+            .front_center()
+            .low_frequency()
+            .side_left()
+            .side_right();
+
+        assert_eq!(
+            positions(channels, true),
+            [
+                ("front left", Position::LeftFront),
+                ("front right", Position::RightFront),
+                ("front center", Position::Center),
+                ("side left", Position::LeftSurround),
+                ("side right", Position::RightSurround),
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_mask_is_read_from_the_format_tag() {
+        let directory = std::env::temp_dir();
+
+        let extensible = directory.join("soft_matrix_test_tones_extensible.wav");
+        let writer = WavWriter::create(
+            &extensible,
+            Channels::new().front_left().front_right(),
+            44100,
+            OutputFormat::default(),
+        )
+        .unwrap();
+        writer.finish().unwrap();
+
+        // A 32-bit float wav with 4 channels and no samples
+        let pcm = directory.join("soft_matrix_test_tones_pcm.wav");
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend(b"fmt \x10\0\0\0");
+        bytes.extend(3u16.to_le_bytes());
+        bytes.extend(4u16.to_le_bytes());
+        bytes.extend(44100u32.to_le_bytes());
+        bytes.extend((44100u32 * 16).to_le_bytes());
+        bytes.extend(16u16.to_le_bytes());
+        bytes.extend(32u16.to_le_bytes());
+        bytes.extend(b"data\0\0\0\0");
+        let riff_size = (bytes.len() as u32 - 8).to_le_bytes();
+        bytes[4..8].copy_from_slice(&riff_size);
+        fs::write(&pcm, bytes).unwrap();
+
+        assert!(has_channel_mask(&extensible).unwrap());
+        assert!(!has_channel_mask(&pcm).unwrap());
+        assert_eq!(
+            read_wav_from_file_path(&pcm)
+                .unwrap()
+                .channels()
+                .channel_mask(),
+            UNMASKED_QUAD
+        );
+
+        fs::remove_file(extensible).unwrap();
+        fs::remove_file(pcm).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use std::f64::consts::PI;
+
+use crate::tone_generator::seconds_to_samples;
+
+/// The default length of the ramps at the start and end of each tone, in seconds
+pub const DEFAULT_FADE_DURATION: f32 = 0.005;
+
+/// The shape of the ramps that fade a tone in and out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FadeShape {
+    /// Half of a Hann window: Smooth, with the shortest transition
+    RaisedCosine,
+    /// Half of a Blackman window: Its spectrum falls off faster than a raised cosine's, at the cost
+    /// of a slower start
+    Blackman,
+}
+
+impl FadeShape {
+    // The gain at `progress` through a fade-in, from 0 at the start to 1 at the end
+    fn gain(&self, progress: f64) -> f64 {
+        let phase = PI * progress;
+        match self {
+            FadeShape::RaisedCosine => 0.5 - 0.5 * phase.cos(),
+            FadeShape::Blackman => 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos(),
+        }
+    }
+}
+
+/// Fades each tone in from silence, and back out to silence, so that starting and stopping the
+/// tone doesn't click. A click is broadband, so an active decoder steers on it instead of the tone
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+    pub shape: FadeShape,
+    /// How long the fade-in and the fade-out each last, in seconds. 0 starts and stops abruptly
+    pub duration: f32,
+}
+
+impl Default for Fade {
+    fn default() -> Fade {
+        Fade {
+            shape: FadeShape::RaisedCosine,
+            duration: DEFAULT_FADE_DURATION,
+        }
+    }
+}
+
+impl Fade {
+    /// Starts and stops each tone abruptly
+    pub fn none() -> Fade {
+        Fade {
+            shape: FadeShape::RaisedCosine,
+            duration: 0.0,
+        }
+    }
+
+    /// The gain for each sample of a signal that lasts `length` samples. Ramps that are longer than
+    /// half of the signal are shortened, so that the fade-in and fade-out don't overlap
+    pub fn envelope(&self, sample_rate: u32, length: usize) -> Vec<f32> {
+        let ramp_length = seconds_to_samples(sample_rate, self.duration).min(length / 2);
+
+        (0..length)
+            .map(|sample_ctr| {
+                let from_edge = sample_ctr.min(length - 1 - sample_ctr);
+                if from_edge < ramp_length {
+                    self.shape
+                        .gain((from_edge as f64 + 0.5) / ramp_length as f64)
+                        as f32
+                } else {
+                    1.0
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope_is_symmetric() {
+        let envelope = Fade::default().envelope(48000, 1000);
+
+        // 5 ms at 48 kHz is a 240-sample ramp
+        assert!(envelope[0] > 0.0 && envelope[0] < 0.001);
+        assert!(envelope[239] < 1.0);
+        assert_eq!(envelope[240], 1.0);
+        for (fade_in, fade_out) in envelope.iter().zip(envelope.iter().rev()) {
+            assert!((fade_in - fade_out).abs() < 1e-6);
+        }
+        for (previous, next) in envelope.iter().zip(envelope.iter().skip(1)).take(240) {
+            assert!(previous < next);
+        }
+    }
+
+    #[test]
+    fn long_ramps_are_clamped_to_half_the_length() {
+        let fade = Fade {
+            shape: FadeShape::RaisedCosine,
+            duration: 1.0,
+        };
+        let envelope = fade.envelope(1000, 100);
+
+        assert_eq!(envelope.len(), 100);
+        assert!(envelope.iter().all(|gain| *gain < 1.0));
+        assert!((envelope[49] - envelope[50]).abs() < 1e-6);
+        assert!(envelope[49] > 0.999);
+    }
+
+    #[test]
+    fn no_fade_is_all_ones() {
+        assert!(Fade::none()
+            .envelope(44100, 100)
+            .iter()
+            .all(|gain| *gain == 1.0));
+        assert!(Fade::default().envelope(44100, 0).is_empty());
+    }
+
+    #[test]
+    fn blackman_starts_at_0_and_ends_at_1() {
+        assert!(FadeShape::Blackman.gain(0.0).abs() < 1e-9);
+        assert!((FadeShape::Blackman.gain(1.0) - 1.0).abs() < 1e-9);
+        assert!((FadeShape::Blackman.gain(0.5) - 0.34).abs() < 1e-9);
+
+        let fade = Fade {
+            shape: FadeShape::Blackman,
+            duration: 0.1,
+        };
+        let envelope = fade.envelope(1000, 1000);
+        assert!(envelope[0] > 0.0 && envelope[0] < 0.001);
+        assert!(envelope[99] > 0.999 && envelope[99] < 1.0);
+        assert_eq!(envelope[100], 1.0);
+    }
+}
@@ -0,0 +1,49 @@
+//! Generates test tones for different matrixes for soft_matrix
+//!
+//! Each matrix implements `MatrixEncoder`, which maps a `Position` or an arbitrary azimuth to
+//! (left total, right total) gains. A `ToneGenerator` writes a tone at each position into a stereo
+//! wav file, with silence between each tone. `discrete::encode_wav` encodes a discrete quad or 5.1
+//! wav file with any matrix, and `discrete::decode_wav` decodes a stereo wav file with one of the
+//! passive `MatrixDecoder`s. A `Program`, read from a JSON file, describes a test sequence of
+//! signals and silences. `analysis::StereoWav` measures the tones in a generated file.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use soft_matrix_test_tones::{matrix::SqMatrix, ToneGenerator};
+//!
+//! let mut tone_generator = ToneGenerator::new(44100, 1000.0, 10000, 1000);
+//! tone_generator
+//!     .write_all_tones(Path::new("sq.wav"), &SqMatrix)
+//!     .unwrap();
+//! ```
+
+pub mod analysis;
+pub mod discrete;
+pub mod fade;
+pub mod hilbert;
+pub mod manifest;
+pub mod matrix;
+pub mod panning;
+pub mod position;
+pub mod program;
+pub mod random;
+pub mod sequence;
+pub mod signal;
+pub mod tone_generator;
+pub mod writer;
+
+pub use fade::{Fade, FadeShape};
+pub use manifest::Manifest;
+pub use matrix::MatrixEncoder;
+pub use position::Position;
+pub use program::Program;
+pub use sequence::{Placement, Tone, ToneSequence};
+pub use signal::{NoiseColor, Signal};
+pub use tone_generator::{FrequencyMode, Segment, ToneGenerator};
+pub use writer::OutputFormat;
+
+// An error for a file that was read, but whose contents don't make sense
+pub(crate) fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
@@ -1,283 +1,529 @@
-use std::{path::Path, sync::Arc};
-
-use rustfft::{num_complex::Complex, Fft, FftPlanner};
-use std::f32::consts::PI;
-use wave_stream::{
-    samples_by_channel::SamplesByChannel,
-    wave_header::{Channels, SampleFormat, WavHeader},
-    wave_writer::RandomAccessWavWriter,
-    write_wav_to_file_path,
-};
+mod options;
 
-const HALF_PI: f32 = PI / 2.0;
+use std::{env, fs, io::ErrorKind, process};
 
-const WINDOW_SIZE: usize = 50;
-const ITERATIONS_PER_TONE: usize = 200;
-const ITERATIONS_PER_SILENCE: usize = 20;
+use std::path::{Path, PathBuf};
 
-struct ToneGenerator {
-    header: WavHeader,
-    window_size: usize,
-    iterations_per_tone: usize,
-    iterations_per_silence: usize,
-    fft_inverse: Arc<dyn Fft<f32>>,
-    scale: f32,
-    scratch: Vec<Complex<f32>>,
+use options::{
+    AnalyzeOptions, Command, ConvertOptions, GenerateOptions, IfExists, RenderOptions,
+    DEFAULT_FREQUENCY,
+};
+use soft_matrix_test_tones::{
+    analysis::{is_stereo, DecodedWav, StereoWav},
+    discrete::{decode_wav, encode_wav},
+    matrix::{find_decoder, load_matrix, matrices},
+    program::ProgramSegment,
+    Manifest, MatrixEncoder, Position, Program, Segment, Signal, ToneGenerator, ToneSequence,
+};
 
-    sample_ctr: usize,
+fn main() {
+    let command = match Command::parse(env::args()) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!();
+            eprintln!("Run with --help for usage");
+            process::exit(1);
+        }
+    };
+
+    match command {
+        Command::Help => print!("{}", options::usage()),
+        Command::ListMatrices => {
+            for matrix in matrices() {
+                println!("{}\t{}", matrix.name(), matrix.description());
+            }
+        }
+        Command::Describe(matrix) => describe(matrix_for(&matrix).as_ref()),
+        Command::Generate(options) => generate(&options),
+        Command::Encode(options) => encode(&options),
+        Command::Decode(options) => decode(&options),
+        Command::Analyze(options) => analyze(&options),
+        Command::Render(options) => render(&options),
+    }
 }
 
-fn main() {
-    println!("Generating test tones for use with soft_matrix");
+// Loads a built-in matrix, or a custom matrix from a .json file, or exits
+fn matrix_for(name: &str) -> Box<dyn MatrixEncoder> {
+    match load_matrix(name) {
+        Ok(encoder) => encoder,
+        Err(err) if err.kind() == ErrorKind::NotFound && !name.ends_with(".json") => {
+            eprintln!("Unknown matrix: {}", name);
+            eprintln!("Run list-matrices to see the supported matrixes");
+            process::exit(1);
+        }
+        Err(err) => {
+            eprintln!("Can not read {}: {}", name, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn describe(encoder: &dyn MatrixEncoder) {
+    println!("{}: {}", encoder.name(), encoder.description());
     println!();
-    println!("Tones are always in the order:");
-    println!("\tcenter");
-    println!("\tright front");
-    println!("\tright middle");
-    println!("\tright rear");
-    println!("\trear center");
-    println!("\tleft rear");
-    println!("\tleft middle");
-    println!("\tleft front");
-
-    let sample_rate = 44100;
-    let header = WavHeader {
-        sample_format: SampleFormat::Float,
-        channels: Channels::new().front_left().front_right(),
-        sample_rate,
-    };
+    println!("position\tLt amplitude\tLt phase\tRt amplitude\tRt phase");
+    for tone in ToneSequence::for_matrix(encoder).tones.iter() {
+        let (left_amplitude, left_phase) = tone.left_total.to_polar();
+        let (right_amplitude, right_phase) = tone.right_total.to_polar();
+        println!(
+            "{}\t{:.3}\t{:.1}°\t{:.3}\t{:.1}°",
+            tone.placement.name(),
+            left_amplitude,
+            left_phase.to_degrees(),
+            right_amplitude,
+            right_phase.to_degrees()
+        );
+    }
+}
 
-    let mut planner = FftPlanner::new();
-    let fft_inverse = planner.plan_fft_inverse(WINDOW_SIZE);
+// Applies the --if-exists policy: Returns false if the file should be skipped
+fn should_write(path: &Path, if_exists: IfExists) -> bool {
+    if path.exists() {
+        match if_exists {
+            IfExists::Overwrite => {}
+            IfExists::Skip => {
+                println!("Skipping {}, it already exists", path.display());
+                return false;
+            }
+            IfExists::Fail => fail_if_exists(path),
+        }
+    }
 
-    let scratch = vec![
-        Complex {
-            re: 0.0f32,
-            im: 0.0f32
-        };
-        fft_inverse.get_inplace_scratch_len()
-    ];
-
-    let mut tone_generator = ToneGenerator {
-        header,
-        window_size: WINDOW_SIZE,
-        iterations_per_tone: ITERATIONS_PER_TONE,
-        iterations_per_silence: ITERATIONS_PER_SILENCE,
-        fft_inverse,
-
-        // rustfft states that the scale is 1/len()
-        // See "noramlization": https://docs.rs/rustfft/latest/rustfft/#normalization
-        scale: 1.0 / (WINDOW_SIZE as f32).sqrt(),
-
-        scratch,
-        sample_ctr: 0,
+    true
+}
+
+// Exits if a file that would be overwritten exists
+fn fail_if_exists(path: &Path) {
+    if path.exists() {
+        eprintln!("{} already exists", path.display());
+        process::exit(1);
+    }
+}
+
+fn generate(options: &GenerateOptions) {
+    // Validate all matrixes before writing anything
+    let encoders = if options.matrices.is_empty() {
+        matrices()
+    } else {
+        options
+            .matrices
+            .iter()
+            .map(|name| matrix_for(name))
+            .collect()
     };
 
-    // default
-    tone_generator.write_all_tones(
-        Path::new("default.wav"),
-        (
-            Complex::from_polar(0.707106781186548, 0.0),
-            Complex::from_polar(0.707106781186548, 0.0),
-        ),
-        (Complex::from_polar(0.0, 0.0), Complex::from_polar(1.0, 0.0)),
-        (
-            Complex::from_polar(0.1, HALF_PI),
-            Complex::from_polar(1.0, 0.0),
-        ),
-        (Complex::from_polar(0.1, PI), Complex::from_polar(1.0, 0.0)),
-        (
-            Complex::from_polar(0.707106781186548, PI),
-            Complex::from_polar(0.707106781186548, 0.0),
-        ),
-        (Complex::from_polar(1.0, PI), Complex::from_polar(0.1, 0.0)),
-        (
-            Complex::from_polar(1.0, PI),
-            Complex::from_polar(0.1, HALF_PI),
-        ),
-        (Complex::from_polar(1.0, 0.0), Complex::from_polar(0.0, 0.0)),
-    );
+    let paths: Vec<PathBuf> = encoders
+        .iter()
+        .map(|encoder| generate_path(options, encoder.as_ref()))
+        .collect();
+    for path in paths.iter() {
+        create_parent_dir(path);
+    }
 
-    let (right_middle_lt, right_middle_rt) = sq_encode(
-        Complex::from_polar(0.0, 0.0),
-        Complex::from_polar(0.707106781186548, 0.0),
-        Complex::from_polar(0.0, 0.0),
-        Complex::from_polar(0.707106781186548, 0.0),
-    );
+    // With --if-exists fail, every file is checked before any are written, so that a failure doesn't
+    // leave only some of the matrixes written
+    if options.if_exists == IfExists::Fail {
+        for path in paths.iter() {
+            fail_if_exists(path);
+            fail_if_exists(&Manifest::path_for(path));
+        }
+    }
 
-    let (rear_center_lt, rear_center_rt) = sq_encode(
-        Complex::from_polar(0.0, 0.0),
-        Complex::from_polar(0.0, 0.0),
-        Complex::from_polar(0.707106781186548, 0.0),
-        Complex::from_polar(0.707106781186548, 0.0),
-    );
+    println!("Generating test tones for use with soft_matrix");
+    println!();
+    match (&options.azimuths, options.sweep_duration) {
+        (Some(azimuths), _) => {
+            println!("Tones are at the azimuths, in order:");
+            for azimuth in azimuths {
+                println!("\t{}°", azimuth);
+            }
+        }
+        (None, Some(_)) => {
+            println!("The tone pans once around the circle, starting at center and moving right")
+        }
+        (None, None) => {
+            println!("Tones are always in the order:");
+            for position in Position::STANDARD {
+                println!("\t{}", position.name());
+            }
+        }
+    }
+    println!();
 
-    let (left_middle_lt, left_middle_rt) = sq_encode(
-        Complex::from_polar(0.707106781186548, 0.0),
-        Complex::from_polar(0.0, 0.0),
-        Complex::from_polar(0.707106781186548, 0.0),
-        Complex::from_polar(0.0, 0.0),
-    );
+    let signals = options.signals();
+    if signals.len() > 1 {
+        println!("Each position is repeated with:");
+        for signal in signals.iter() {
+            println!("\t{}", signal.describe());
+        }
+    } else {
+        println!("Each position is a {}", signals[0].describe());
+    }
+    println!();
 
-    // sq
-    tone_generator.write_all_tones(
-        Path::new("sq.wav"),
-        (Complex::from_polar(0.707106781186548, 0.0), Complex::from_polar(0.707106781186548, 0.0)),
-        (Complex::from_polar(0.0, 0.0), Complex::from_polar(1.0, 0.0)),
-        (right_middle_lt, right_middle_rt),
-        (
-            Complex::from_polar(0.7, 0.0),
-            Complex::from_polar(0.7, HALF_PI),
-        ),
-        (
-            Complex::from_polar(0.7, 0.0) + Complex::from_polar(0.7, 0.0 - HALF_PI),
-            Complex::from_polar(0.7, HALF_PI) + Complex::from_polar(0.7, PI),
-        ),
-        (rear_center_lt, rear_center_rt),
-        (left_middle_lt, left_middle_rt),
-        (Complex::from_polar(1.0, 0.0), Complex::from_polar(0.0, 0.0)),
+    let mut tone_generator = ToneGenerator::with_durations(
+        options.sample_rate,
+        DEFAULT_FREQUENCY,
+        options.tone_duration,
+        options.silence_duration,
     );
+    tone_generator.set_signals(signals);
+    tone_generator.set_fade(options.fade);
+    tone_generator.set_output_format(options.output_format);
+
+    for (encoder, path) in encoders.iter().zip(paths) {
+        if !should_write(&path, options.if_exists) {
+            continue;
+        }
+
+        println!("Writing {}", path.display());
+        let result = match (&options.azimuths, options.sweep_duration) {
+            (Some(azimuths), _) => {
+                tone_generator.write_azimuth_tones(&path, encoder.as_ref(), azimuths)
+            }
+            (None, Some(sweep_duration)) => {
+                tone_generator.write_panning_sweep(&path, encoder.as_ref(), sweep_duration)
+            }
+            (None, None) => {
+                for position in encoder.positions().iter().skip(Position::STANDARD.len()) {
+                    println!("\tfollowed by {}", position.name());
+                }
+
+                tone_generator.write_all_tones(&path, encoder.as_ref())
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("Can not write {}: {}", path.display(), err);
+            process::exit(1);
+        }
+
+        let manifest = match (&options.azimuths, options.sweep_duration) {
+            (Some(azimuths), _) => tone_generator.manifest(
+                encoder.as_ref(),
+                &ToneSequence::for_azimuths(encoder.as_ref(), azimuths),
+            ),
+            (None, Some(sweep_duration)) => {
+                tone_generator.panning_sweep_manifest(encoder.as_ref(), sweep_duration)
+            }
+            (None, None) => tone_generator.manifest(
+                encoder.as_ref(),
+                &ToneSequence::for_matrix(encoder.as_ref()),
+            ),
+        };
+        write_manifest(&path, &manifest);
+    }
 }
 
-fn sq_encode(
-    left_front: Complex<f32>,
-    right_front: Complex<f32>,
-    left_rear: Complex<f32>,
-    right_rear: Complex<f32>,
-) -> (Complex<f32>, Complex<f32>) {
-    let (left_back_amplitude, left_back_phase) = left_rear.to_polar();
-    let (right_back_amplitude, right_back_phase) = right_rear.to_polar();
-
-    let left_back_for_left_total =
-        Complex::from_polar(0.7 * left_back_amplitude, left_back_phase - HALF_PI);
-    let right_back_for_left_total =
-        Complex::from_polar(0.7 * right_back_amplitude, right_back_phase);
-    let left_total = left_front + left_back_for_left_total + right_back_for_left_total;
-
-    let left_back_for_right_total =
-        Complex::from_polar(0.7 * left_back_amplitude, left_back_phase + PI);
-    let right_back_for_right_total =
-        Complex::from_polar(0.7 * right_back_amplitude, right_back_phase + HALF_PI);
-    let right_total = right_front + left_back_for_right_total + right_back_for_right_total;
-
-    (left_front + left_total, right_front + right_total)
+// Where generate writes a matrix's wav
+fn generate_path(options: &GenerateOptions, encoder: &dyn MatrixEncoder) -> PathBuf {
+    match &options.output_file {
+        Some(output_file) => options.output_dir.join(output_file),
+        None => match (&options.azimuths, options.sweep_duration) {
+            (Some(_), _) => options
+                .output_dir
+                .join(format!("{}_azimuths.wav", encoder.name())),
+            (None, Some(_)) => options
+                .output_dir
+                .join(format!("{}_sweep.wav", encoder.name())),
+            (None, None) => options.output_dir.join(format!("{}.wav", encoder.name())),
+        },
+    }
 }
 
-impl ToneGenerator {
-    pub fn write_all_tones(
-        &mut self,
-        path: &Path,
-        center: (Complex<f32>, Complex<f32>),
-        right_front: (Complex<f32>, Complex<f32>),
-        right_middle: (Complex<f32>, Complex<f32>),
-        right_rear: (Complex<f32>, Complex<f32>),
-        rear_center: (Complex<f32>, Complex<f32>),
-        left_rear: (Complex<f32>, Complex<f32>),
-        left_middle: (Complex<f32>, Complex<f32>),
-        left_front: (Complex<f32>, Complex<f32>),
-    ) {
-        self.sample_ctr = 0;
+// Creates the directory that a file will be written into, before anything is printed about it
+fn create_parent_dir(path: &Path) {
+    if let Some(directory) = path.parent() {
+        if let Err(err) = fs::create_dir_all(directory) {
+            eprintln!("Can not create {}: {}", directory.display(), err);
+            process::exit(1);
+        }
+    }
+}
 
-        let outfile = write_wav_to_file_path(path, self.header).unwrap();
-        let mut writer = outfile.get_random_access_f32_writer().unwrap();
+// Writes the manifest next to the wav
+fn write_manifest(wav_path: &Path, manifest: &Manifest) {
+    let path = Manifest::path_for(wav_path);
+    if let Err(err) = manifest.write(&path) {
+        eprintln!("Can not write {}: {}", path.display(), err);
+        process::exit(1);
+    }
+}
 
-        self.write_silence(&mut writer);
+fn encode(options: &ConvertOptions) {
+    let encoder = matrix_for(&options.matrix);
 
-        // Center
-        self.write_tones(&mut writer, self.create_window(center));
-        self.write_silence(&mut writer);
+    let output = options.output(encoder.name());
+    if !should_write(&output, options.if_exists) {
+        return;
+    }
 
-        // Right front
-        self.write_tones(&mut writer, self.create_window(right_front));
-        self.write_silence(&mut writer);
+    create_parent_dir(&output);
 
-        // Right middle
-        self.write_tones(&mut writer, self.create_window(right_middle));
-        self.write_silence(&mut writer);
+    println!(
+        "Encoding {} into {} with {}",
+        options.input.display(),
+        output.display(),
+        encoder.name()
+    );
+    match encode_wav(
+        &options.input,
+        &output,
+        encoder.as_ref(),
+        options.output_format,
+    ) {
+        Ok(discrete_channels) => {
+            for channel in discrete_channels {
+                println!("\t{} as {}", channel.name, channel.position.name());
+            }
+        }
+        Err(err) => {
+            eprintln!("Can not encode {}: {}", options.input.display(), err);
+            process::exit(1);
+        }
+    }
+}
 
-        // Right rear
-        self.write_tones(&mut writer, self.create_window(right_rear));
-        self.write_silence(&mut writer);
+fn decode(options: &ConvertOptions) {
+    let decoder = match find_decoder(&options.matrix) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("There is no passive decoder for {}", options.matrix);
+            process::exit(1);
+        }
+    };
 
-        // Rear center
-        self.write_tones(&mut writer, self.create_window(rear_center));
-        self.write_silence(&mut writer);
+    let output = options.output(&format!("{}_decoded", decoder.name()));
+    if !should_write(&output, options.if_exists) {
+        return;
+    }
 
-        // Left rear
-        self.write_tones(&mut writer, self.create_window(left_rear));
-        self.write_silence(&mut writer);
+    create_parent_dir(&output);
 
-        // Left middle
-        self.write_tones(&mut writer, self.create_window(left_middle));
-        self.write_silence(&mut writer);
+    println!(
+        "Decoding {} into {} with {}",
+        options.input.display(),
+        output.display(),
+        decoder.description()
+    );
+    if let Err(err) = decode_wav(
+        &options.input,
+        &output,
+        decoder.as_ref(),
+        options.output_format,
+    ) {
+        eprintln!("Can not decode {}: {}", options.input.display(), err);
+        process::exit(1);
+    }
+}
 
-        // Left front
-        self.write_tones(&mut writer, self.create_window(left_front));
-        self.write_silence(&mut writer);
+fn render(options: &RenderOptions) {
+    let program = match Program::read(&options.program) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("Can not read {}: {}", options.program.display(), err);
+            process::exit(1);
+        }
+    };
 
-        writer.flush().unwrap();
+    let output = options.output();
+    if !should_write(&output, options.if_exists) {
+        return;
+    }
+    if options.if_exists == IfExists::Fail {
+        fail_if_exists(&Manifest::path_for(&output));
     }
 
-    fn create_window(
-        &self,
-        tones: (Complex<f32>, Complex<f32>),
-    ) -> (Vec<Complex<f32>>, Vec<Complex<f32>>) {
-        let (left_total_tone, right_total_tone) = tones;
-
-        let mut right_total_window = vec![Complex::new(0.0, 0.0); self.window_size];
-        right_total_window[1] = right_total_tone;
-        right_total_window[self.window_size - 1] = Complex {
-            re: right_total_tone.re,
-            im: -1.0 * right_total_tone.im,
-        };
+    create_parent_dir(&output);
 
-        let mut left_total_window = vec![Complex::new(0.0, 0.0); self.window_size];
-        left_total_window[1] = left_total_tone;
-        left_total_window[self.window_size - 1] = Complex {
-            re: left_total_tone.re,
-            im: -1.0 * left_total_tone.im,
-        };
+    println!(
+        "Rendering {} into {}",
+        options.program.display(),
+        output.display()
+    );
+    for segment in program.segments.iter() {
+        match segment {
+            ProgramSegment::Silence { duration } => println!("\t{} s of silence", duration),
+            ProgramSegment::Signal {
+                signal,
+                matrix,
+                tone,
+                duration,
+                ..
+            } => println!(
+                "\t{} s of {} at {} with {}",
+                duration,
+                signal.describe(),
+                tone.placement.name(),
+                matrix
+            ),
+        }
+    }
 
-        (left_total_window, right_total_window)
+    // The program sets its own signals and durations
+    let mut tone_generator =
+        ToneGenerator::with_durations(options.sample_rate, DEFAULT_FREQUENCY, 0.0, 0.0);
+    tone_generator.set_fade(options.fade);
+    tone_generator.set_output_format(options.output_format);
+    if let Err(err) = tone_generator.write_program(&output, &program) {
+        eprintln!("Can not write {}: {}", output.display(), err);
+        process::exit(1);
     }
 
-    fn write_tones(
-        &mut self,
-        writer: &mut RandomAccessWavWriter<f32>,
-        windows: (Vec<Complex<f32>>, Vec<Complex<f32>>),
-    ) {
-        let (mut left_total_window, mut right_total_window) = windows;
-        self.fft_inverse
-            .process_with_scratch(&mut left_total_window, &mut self.scratch);
-        self.fft_inverse
-            .process_with_scratch(&mut right_total_window, &mut self.scratch);
-
-        for _iteration in 0..self.iterations_per_tone {
-            for window_ctr in 0..self.window_size {
-                let samples_by_channel = SamplesByChannel::new()
-                    .front_left(self.scale * left_total_window[window_ctr].re)
-                    .front_right(self.scale * right_total_window[window_ctr].re);
-
-                writer
-                    .write_samples(self.sample_ctr, samples_by_channel)
-                    .unwrap();
-
-                self.sample_ctr += 1;
+    write_manifest(&output, &tone_generator.program_manifest(&program));
+}
+
+fn analyze(options: &AnalyzeOptions) {
+    let encoder = matrix_for(&options.matrix);
+
+    let result = match is_stereo(&options.input) {
+        Ok(true) => StereoWav::read(&options.input)
+            .map(|wav| analyze_stereo(options, encoder.as_ref(), &wav)),
+        Ok(false) => DecodedWav::read(&options.input)
+            .map(|wav| analyze_decoded(options, encoder.as_ref(), &wav)),
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Can not read {}: {}", options.input.display(), err);
+        process::exit(1);
+    }
+}
+
+// Where the tones in a wav that was generated with the options are
+fn analysis_segments(
+    options: &AnalyzeOptions,
+    encoder: &dyn MatrixEncoder,
+    sample_rate: u32,
+) -> Vec<Segment> {
+    let signals = options.signals();
+    for signal in signals.iter() {
+        if let Signal::Tone { frequencies } = signal {
+            for frequency in frequencies {
+                if *frequency >= sample_rate as f32 / 2.0 {
+                    eprintln!(
+                        "The frequency ({} Hz) must be below half the wav's sample rate ({} Hz)",
+                        frequency,
+                        sample_rate as f32 / 2.0
+                    );
+                    process::exit(1);
+                }
             }
         }
     }
 
-    fn write_silence(&mut self, writer: &mut RandomAccessWavWriter<f32>) {
-        for _ in 0..self.iterations_per_silence {
-            for _ in 0..self.window_size {
-                let samples_by_channel = SamplesByChannel::new().front_left(0.0).front_right(0.0);
+    let mut tone_generator = ToneGenerator::with_durations(
+        sample_rate,
+        DEFAULT_FREQUENCY,
+        options.tone_duration,
+        options.silence_duration,
+    );
+    tone_generator.set_signals(signals);
 
-                writer
-                    .write_samples(self.sample_ctr, samples_by_channel)
-                    .unwrap();
+    let sequence = match &options.azimuths {
+        Some(azimuths) => ToneSequence::for_azimuths(encoder, azimuths),
+        None => ToneSequence::for_matrix(encoder),
+    };
 
-                self.sample_ctr += 1;
-            }
+    tone_generator.segments(&sequence)
+}
+
+fn analyze_stereo(options: &AnalyzeOptions, encoder: &dyn MatrixEncoder, wav: &StereoWav) {
+    let segments = analysis_segments(options, encoder, wav.sample_rate);
+    let analyses = match wav.analyze(&segments) {
+        Ok(analyses) => analyses,
+        Err(err) => {
+            eprintln!("Can not analyze {}: {}", options.input.display(), err);
+            process::exit(1);
+        }
+    };
+
+    println!("{} analyzed as {}", options.input.display(), encoder.name());
+    println!();
+    println!(
+        "position\tfrequency\tLt level\tRt level\tLt/Rt\tLt-Rt phase\tazimuth\timplied azimuth"
+    );
+    for analysis in analyses.iter() {
+        for tone in analysis.tones.iter() {
+            println!(
+                "{}\t{} Hz\t{:.1} dB\t{:.1} dB\t{:.1} dB\t{:.1}°\t{}°\t{}°",
+                analysis.segment.tone.placement.name(),
+                tone.frequency,
+                20.0 * tone.left_total.norm().log10(),
+                20.0 * tone.right_total.norm().log10(),
+                tone.level_difference(),
+                tone.phase_difference(),
+                analysis.segment.tone.placement.azimuth(),
+                tone.implied_azimuth(encoder)
+            );
+        }
+    }
+}
+
+fn analyze_decoded(options: &AnalyzeOptions, encoder: &dyn MatrixEncoder, wav: &DecodedWav) {
+    let segments = analysis_segments(options, encoder, wav.sample_rate);
+    let latency = options
+        .latency
+        .unwrap_or_else(|| wav.latency(&segments, options.fade));
+    let analyses = match wav.analyze(&segments, latency) {
+        Ok(analyses) => analyses,
+        Err(err) => {
+            eprintln!("Can not analyze {}: {}", options.input.display(), err);
+            process::exit(1);
+        }
+    };
+
+    println!(
+        "{} analyzed as decoded {}, with a latency of {} samples",
+        options.input.display(),
+        encoder.name(),
+        latency
+    );
+    println!();
+
+    let channel_names: Vec<&str> = wav.channels.iter().map(|channel| channel.name).collect();
+    println!(
+        "position\tsignal\t{}\tseparation\tresult",
+        channel_names.join("\t")
+    );
+
+    let mut failures = 0;
+    for analysis in analyses.iter() {
+        let levels: Vec<String> = analysis
+            .levels
+            .iter()
+            .zip(analysis.intended.iter())
+            .map(|(level, intended)| {
+                // Intended channels are marked with a *
+                format!("{:.1} dB{}", level, if *intended { "*" } else { "" })
+            })
+            .collect();
+
+        let separation = analysis.separation();
+        let passed = separation >= options.min_separation;
+        if !passed {
+            failures += 1;
         }
+
+        println!(
+            "{}\t{}\t{}\t{:.1} dB\t{}",
+            analysis.segment.tone.placement.name(),
+            analysis.segment.signal.describe(),
+            levels.join("\t"),
+            separation,
+            if passed { "pass" } else { "fail" }
+        );
+    }
+
+    println!();
+    println!(
+        "{} of {} passed with at least {} dB of separation. * marks the channels each position should be decoded to",
+        analyses.len() - failures,
+        analyses.len(),
+        options.min_separation
+    );
+
+    if failures > 0 {
+        process::exit(1);
     }
 }
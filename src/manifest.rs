@@ -0,0 +1,80 @@
+use std::{
+    fs::File,
+    io::Result,
+    path::{Path, PathBuf},
+};
+
+use rustfft::num_complex::Complex;
+use serde::Serialize;
+
+use crate::signal::Signal;
+
+/// Machine-readable ground truth for a generated wav: Where each segment is, and exactly how it was
+/// encoded. It's written as JSON next to the wav
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Manifest {
+    pub sample_rate: u32,
+    pub segments: Vec<ManifestSegment>,
+}
+
+/// A signal in a generated wav
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ManifestSegment {
+    /// The label of the cue at the segment's start
+    pub label: String,
+    /// The first sample of the signal
+    pub start: usize,
+    /// How many samples the signal lasts
+    pub length: usize,
+    /// The position's name, or None when the signal is at an arbitrary azimuth or pans
+    pub position: Option<&'static str>,
+    /// In degrees: 0 is center, positive is to the right, and 180 is rear. A panning sweep moves
+    /// through every azimuth, so it has no azimuth or gains
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub azimuth: Option<f32>,
+    pub matrix: String,
+    pub signal: Signal,
+    /// The signal's peak amplitude before the gains are applied, where 1 is full scale
+    pub amplitude: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub left_total: Option<ManifestGain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub right_total: Option<ManifestGain>,
+}
+
+/// A complex gain, both as its real and imaginary parts and as its amplitude and phase
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct ManifestGain {
+    pub re: f32,
+    pub im: f32,
+    pub amplitude: f32,
+    /// In degrees
+    pub phase: f32,
+}
+
+impl From<Complex<f32>> for ManifestGain {
+    fn from(gain: Complex<f32>) -> ManifestGain {
+        let (amplitude, phase) = gain.to_polar();
+        ManifestGain {
+            re: gain.re,
+            im: gain.im,
+            amplitude,
+            phase: phase.to_degrees(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Writes the manifest as JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Where the manifest for a wav is written: Next to the wav, ending in .manifest.json instead
+    /// of .wav
+    pub fn path_for(wav_path: &Path) -> PathBuf {
+        wav_path.with_extension("manifest.json")
+    }
+}
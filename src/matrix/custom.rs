@@ -0,0 +1,311 @@
+use std::{fs, io::Result, path::Path};
+
+use rustfft::num_complex::Complex;
+use serde::Deserialize;
+
+use crate::{invalid, panning::normalize_azimuth, position::Position};
+
+use super::{encode_discrete, Gains, MatrixEncoder};
+
+/// A matrix that is defined in a JSON file instead of in code. Each of its discrete channels has
+/// (left total, right total) coefficients, and azimuths between the channels are panned between
+/// the two nearest channels, like `sq_encode` combines the fronts and rears
+pub struct CustomMatrix {
+    name: String,
+    description: String,
+    // (azimuth, gains) for each discrete channel
+    channels: Vec<(f32, Gains)>,
+    positions: Vec<Position>,
+}
+
+impl CustomMatrix {
+    /// Reads a matrix from a JSON file
+    pub fn read(path: &Path) -> Result<CustomMatrix> {
+        CustomMatrix::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a matrix from JSON, such as:
+    ///
+    /// ```json
+    /// {
+    ///     "name": "homebrew",
+    ///     "description": "A homebrew quad matrix",
+    ///     "channels": [
+    ///         { "position": "left front", "left_total": { "amplitude": 1 },
+    ///           "right_total": { "amplitude": 0 } },
+    ///         { "position": "right front", "left_total": { "amplitude": 0 },
+    ///           "right_total": { "amplitude": 1 } },
+    ///         { "azimuth": -135, "left_total": { "amplitude": 0.7, "phase": -90 },
+    ///           "right_total": { "amplitude": 0.7, "phase": 180 } },
+    ///         { "azimuth": 135, "left_total": { "amplitude": 0.7 },
+    ///           "right_total": { "amplitude": 0.7, "phase": 90 } }
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// Each channel is at a "position" (such as "left rear") or an "azimuth" in degrees. Phases are
+    /// in degrees, and default to 0. The name is used on the command line and in file names, so it
+    /// can only have letters, numbers, "_", and "-". Tones are generated at the standard positions,
+    /// followed by any other positions that channels are at
+    pub fn parse(json: &str) -> Result<CustomMatrix> {
+        let file: MatrixFile = serde_json::from_str(json)?;
+
+        let is_valid_name = !file.name.is_empty()
+            && file
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !is_valid_name {
+            return Err(invalid(format!(
+                "The matrix's name \"{}\" can only have letters, numbers, \"_\", and \"-\"",
+                file.name
+            )));
+        }
+
+        if file.channels.len() < 2 {
+            return Err(invalid("A matrix needs at least two channels".to_string()));
+        }
+
+        let mut positions = Position::STANDARD.to_vec();
+        let mut channels = Vec::new();
+        for (index, channel) in file.channels.iter().enumerate() {
+            let in_channel =
+                |message: String| invalid(format!("Channel {}: {}", index + 1, message));
+
+            let azimuth = channel.azimuth(&mut positions).map_err(in_channel)?;
+
+            if channels
+                .iter()
+                .any(|(other, _)| normalize_azimuth(*other) == normalize_azimuth(azimuth))
+            {
+                return Err(in_channel(format!(
+                    "Another channel is already at {}°",
+                    azimuth
+                )));
+            }
+
+            let gains = (
+                channel.left_total.gain().map_err(in_channel)?,
+                channel.right_total.gain().map_err(in_channel)?,
+            );
+            channels.push((azimuth, gains));
+        }
+
+        let description = file
+            .description
+            .unwrap_or_else(|| format!("Custom matrix {}", file.name));
+
+        Ok(CustomMatrix {
+            name: file.name,
+            description,
+            channels,
+            positions,
+        })
+    }
+}
+
+impl MatrixEncoder for CustomMatrix {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        self.positions.clone()
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        encode_discrete(azimuth, &self.channels)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatrixFile {
+    name: String,
+    description: Option<String>,
+    channels: Vec<ChannelFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChannelFile {
+    position: Option<String>,
+    azimuth: Option<f32>,
+    left_total: CoefficientFile,
+    right_total: CoefficientFile,
+}
+
+impl ChannelFile {
+    // Adds a named position that isn't a standard position to the positions that tones are
+    // generated at
+    fn azimuth(&self, positions: &mut Vec<Position>) -> std::result::Result<f32, String> {
+        match (&self.position, self.azimuth) {
+            (Some(name), None) => {
+                let position = Position::from_name(name)
+                    .ok_or_else(|| format!("Unknown position \"{}\"", name))?;
+                if !positions.contains(&position) {
+                    positions.push(position);
+                }
+
+                Ok(position.azimuth())
+            }
+            (None, Some(azimuth)) if azimuth.is_finite() => Ok(azimuth),
+            (None, Some(_)) => Err("\"azimuth\" must be a finite number".to_string()),
+            _ => Err("A channel needs either a \"position\" or an \"azimuth\"".to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoefficientFile {
+    amplitude: f32,
+    // Degrees
+    #[serde(default)]
+    phase: f32,
+}
+
+impl CoefficientFile {
+    fn gain(&self) -> std::result::Result<Complex<f32>, String> {
+        if !(self.amplitude.is_finite() && self.amplitude >= 0.0) {
+            return Err("\"amplitude\" must be at least 0".to_string());
+        }
+
+        if !self.phase.is_finite() {
+            return Err("\"phase\" must be a finite number".to_string());
+        }
+
+        Ok(Complex::from_polar(self.amplitude, self.phase.to_radians()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::SqMatrix;
+
+    fn parse_error(json: &str) -> String {
+        match CustomMatrix::parse(json) {
+            Ok(_) => panic!("Expected an error"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_sq_coefficients() {
+        let matrix = CustomMatrix::read(Path::new("matrices/homebrew.json")).unwrap();
+
+        assert_eq!(matrix.name(), "homebrew");
+        for position in Position::STANDARD {
+            let (left_total, right_total) = matrix.encode_position(position);
+            let (sq_left_total, sq_right_total) = SqMatrix.encode_position(position);
+            assert!(
+                (left_total - sq_left_total).norm() < 1e-3,
+                "{}",
+                position.name()
+            );
+            assert!(
+                (right_total - sq_right_total).norm() < 1e-3,
+                "{}",
+                position.name()
+            );
+        }
+    }
+
+    #[test]
+    fn adds_positions_that_arent_standard() {
+        let matrix = CustomMatrix::parse(
+            r#"{ "name": "sides", "channels": [
+                { "position": "left front", "left_total": { "amplitude": 1 },
+                  "right_total": { "amplitude": 0 } },
+                { "position": "left surround", "left_total": { "amplitude": 0.7 },
+                  "right_total": { "amplitude": 0.7, "phase": 90 } }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(matrix.description(), "Custom matrix sides");
+        assert_eq!(matrix.positions().last(), Some(&Position::LeftSurround));
+        assert!(!Position::STANDARD.contains(&Position::LeftSurround));
+    }
+
+    #[test]
+    fn rejects_duplicate_azimuths() {
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "duplicate", "channels": [
+                    { "azimuth": 180, "left_total": { "amplitude": 1 },
+                      "right_total": { "amplitude": 0 } },
+                    { "azimuth": -180, "left_total": { "amplitude": 0 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "Channel 2: Another channel is already at -180°"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "my matrix", "channels": [
+                    { "position": "left front", "left_total": { "amplitude": 1 },
+                      "right_total": { "amplitude": 0 } },
+                    { "position": "right front", "left_total": { "amplitude": 0 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "The matrix's name \"my matrix\" can only have letters, numbers, \"_\", and \"-\""
+        );
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "upstairs", "channels": [
+                    { "position": "left front", "left_total": { "amplitude": 1 },
+                      "right_total": { "amplitude": 0 } },
+                    { "position": "upstairs", "left_total": { "amplitude": 0 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "Channel 2: Unknown position \"upstairs\""
+        );
+    }
+
+    #[test]
+    fn rejects_bad_channels() {
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "mono", "channels": [
+                    { "position": "center", "left_total": { "amplitude": 1 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "A matrix needs at least two channels"
+        );
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "negative", "channels": [
+                    { "position": "left front", "left_total": { "amplitude": -1 },
+                      "right_total": { "amplitude": 0 } },
+                    { "position": "right front", "left_total": { "amplitude": 0 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "Channel 1: \"amplitude\" must be at least 0"
+        );
+        assert_eq!(
+            parse_error(
+                r#"{ "name": "both", "channels": [
+                    { "position": "left front", "azimuth": -45,
+                      "left_total": { "amplitude": 1 }, "right_total": { "amplitude": 0 } },
+                    { "position": "right front", "left_total": { "amplitude": 0 },
+                      "right_total": { "amplitude": 1 } }
+                ] }"#
+            ),
+            "Channel 1: A channel needs either a \"position\" or an \"azimuth\""
+        );
+    }
+}
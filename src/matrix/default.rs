@@ -0,0 +1,57 @@
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, PI};
+
+use rustfft::num_complex::Complex;
+
+use crate::position::Position;
+
+use super::{encode_discrete, Gains, MatrixEncoder};
+
+/// soft_matrix's default matrix
+pub struct DefaultMatrix;
+
+impl MatrixEncoder for DefaultMatrix {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    fn description(&self) -> &str {
+        "soft_matrix's default matrix"
+    }
+
+    fn encode_position(&self, position: Position) -> Gains {
+        match position {
+            Position::Center => (
+                Complex::from_polar(FRAC_1_SQRT_2, 0.0),
+                Complex::from_polar(FRAC_1_SQRT_2, 0.0),
+            ),
+            Position::RightFront => (Complex::from_polar(0.0, 0.0), Complex::from_polar(1.0, 0.0)),
+            Position::RightMiddle => (
+                Complex::from_polar(0.1, FRAC_PI_2),
+                Complex::from_polar(1.0, 0.0),
+            ),
+            Position::RightRear => (Complex::from_polar(0.1, PI), Complex::from_polar(1.0, 0.0)),
+            Position::RearCenter => (
+                Complex::from_polar(FRAC_1_SQRT_2, PI),
+                Complex::from_polar(FRAC_1_SQRT_2, 0.0),
+            ),
+            Position::LeftRear => (Complex::from_polar(1.0, PI), Complex::from_polar(0.1, 0.0)),
+            Position::LeftMiddle => (
+                Complex::from_polar(1.0, PI),
+                Complex::from_polar(0.1, FRAC_PI_2),
+            ),
+            Position::LeftFront => (Complex::from_polar(1.0, 0.0), Complex::from_polar(0.0, 0.0)),
+            _ => self.encode_azimuth(position.azimuth()),
+        }
+    }
+
+    // The default matrix is only defined at the standard positions, so other azimuths are
+    // panned between the two nearest positions
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        let channels: Vec<(f32, Gains)> = Position::STANDARD
+            .iter()
+            .map(|position| (position.azimuth(), self.encode_position(*position)))
+            .collect();
+
+        encode_discrete(azimuth, &channels)
+    }
+}
@@ -0,0 +1,143 @@
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+use rustfft::num_complex::Complex;
+
+use crate::{panning::pan, position::Position};
+
+use super::{Gains, MatrixDecoder, MatrixEncoder};
+
+// Azimuths of the discrete speakers: left, center, right, surround
+const SPEAKERS: [f32; 4] = [-45.0, 0.0, 45.0, 180.0];
+
+/// Dolby Stereo / Dolby Surround / Pro Logic (4-channel LCRS)
+pub struct DolbySurroundMatrix;
+
+impl MatrixEncoder for DolbySurroundMatrix {
+    fn name(&self) -> &str {
+        "dolby_surround"
+    }
+
+    fn description(&self) -> &str {
+        "Dolby Stereo / Dolby Surround / Pro Logic (LCRS)"
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        let gains = pan(azimuth, &SPEAKERS);
+
+        dolby_surround_encode(
+            Complex::from_polar(gains[0], 0.0),
+            Complex::from_polar(gains[1], 0.0),
+            Complex::from_polar(gains[2], 0.0),
+            Complex::from_polar(gains[3], 0.0),
+        )
+    }
+}
+
+/// Encodes left, center, right and (mono) surround into Dolby's (left total, right total). Center is
+/// mixed into both totals at -3 dB; surround is mixed at -3 dB, with a -90° shift into left total
+/// and +90° into right total
+pub fn dolby_surround_encode(
+    left: Complex<f32>,
+    center: Complex<f32>,
+    right: Complex<f32>,
+    surround: Complex<f32>,
+) -> Gains {
+    let surround_for_left_total = surround * Complex::from_polar(FRAC_1_SQRT_2, -FRAC_PI_2);
+    let surround_for_right_total = surround * Complex::from_polar(FRAC_1_SQRT_2, FRAC_PI_2);
+
+    let left_total = left + center * FRAC_1_SQRT_2 + surround_for_left_total;
+    let right_total = right + center * FRAC_1_SQRT_2 + surround_for_right_total;
+
+    (left_total, right_total)
+}
+
+/// Dolby Surround's passive decoder, without Pro Logic steering
+pub struct DolbySurroundDecoder;
+
+impl MatrixDecoder for DolbySurroundDecoder {
+    fn name(&self) -> &str {
+        "dolby_surround"
+    }
+
+    fn description(&self) -> &str {
+        "Dolby Surround passive decoder (LCRS), without Pro Logic steering"
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        vec![
+            Position::LeftFront,
+            Position::RightFront,
+            Position::Center,
+            Position::RearCenter,
+        ]
+    }
+
+    fn decode(&self, left_total: Complex<f32>, right_total: Complex<f32>) -> Vec<Complex<f32>> {
+        let (left, center, right, surround) = dolby_surround_decode(left_total, right_total);
+        vec![left, right, center, surround]
+    }
+}
+
+/// Decodes Dolby's (left total, right total) into (left, center, right, surround). Center is the
+/// sum of the totals, and surround is their difference, with the phase shifts from
+/// `dolby_surround_encode` reversed
+pub fn dolby_surround_decode(
+    left_total: Complex<f32>,
+    right_total: Complex<f32>,
+) -> (Complex<f32>, Complex<f32>, Complex<f32>, Complex<f32>) {
+    let center = (left_total + right_total) * FRAC_1_SQRT_2;
+    let surround = (left_total * Complex::from_polar(FRAC_1_SQRT_2, FRAC_PI_2))
+        + (right_total * Complex::from_polar(FRAC_1_SQRT_2, -FRAC_PI_2));
+
+    (left_total, center, right_total, surround)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{assert_decoded_levels, assert_gains};
+
+    #[test]
+    fn encodes_published_coefficients() {
+        let gains = |position| DolbySurroundMatrix.encode_position(position);
+
+        assert_gains(
+            gains(Position::LeftFront),
+            Complex::new(1.0, 0.0),
+            Complex::new(0.0, 0.0),
+        );
+        assert_gains(
+            gains(Position::Center),
+            Complex::new(0.707, 0.0),
+            Complex::new(0.707, 0.0),
+        );
+        assert_gains(
+            gains(Position::RightFront),
+            Complex::new(0.0, 0.0),
+            Complex::new(1.0, 0.0),
+        );
+        assert_gains(
+            gains(Position::RearCenter),
+            Complex::new(0.0, -0.707),
+            Complex::new(0.0, 0.707),
+        );
+    }
+
+    // Levels are left, right, center, surround. A passive decoder separates center from surround,
+    // but not from left and right
+    #[test]
+    fn decodes_what_it_encodes() {
+        assert_decoded_levels(
+            &DolbySurroundMatrix,
+            &DolbySurroundDecoder,
+            Position::Center,
+            &[0.707, 0.707, 1.0, 0.0],
+        );
+        assert_decoded_levels(
+            &DolbySurroundMatrix,
+            &DolbySurroundDecoder,
+            Position::RearCenter,
+            &[0.707, 0.707, 0.0, 1.0],
+        );
+    }
+}
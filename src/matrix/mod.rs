@@ -0,0 +1,182 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use rustfft::num_complex::Complex;
+
+use crate::{panning::pan, position::Position};
+
+mod custom;
+mod default;
+mod dolby_surround;
+mod pro_logic_2;
+mod qs;
+mod sq;
+mod uhj;
+
+pub use custom::CustomMatrix;
+pub use default::DefaultMatrix;
+pub use dolby_surround::{
+    dolby_surround_decode, dolby_surround_encode, DolbySurroundDecoder, DolbySurroundMatrix,
+};
+pub use pro_logic_2::{pro_logic_2_encode, ProLogic2Matrix};
+pub use qs::{qs_decode, qs_encode, QsDecoder, QsMatrix};
+pub use sq::{sq_decode, sq_encode, SqDecoder, SqMatrix};
+pub use uhj::{uhj_decode, uhj_encode, UhjDecoder, UhjMatrix};
+
+// Azimuths of a quadraphonic matrix's discrete speakers: left front, right front, left rear, right rear
+pub(crate) const QUAD_SPEAKERS: [f32; 4] = [-45.0, 45.0, -135.0, 135.0];
+
+/// (left total, right total) gains
+pub type Gains = (Complex<f32>, Complex<f32>);
+
+/// Encodes a sound source into a matrix's (left total, right total) gains. Each gain is a complex
+/// number: Its amplitude scales the tone, and its angle shifts the tone's phase
+pub trait MatrixEncoder {
+    /// The short name used on the command line and for file names, such as "sq"
+    fn name(&self) -> &str;
+
+    /// A human-readable description of the matrix
+    fn description(&self) -> &str;
+
+    /// The positions that tones are generated at, in order
+    fn positions(&self) -> Vec<Position> {
+        Position::STANDARD.to_vec()
+    }
+
+    /// Encodes a tone at one of the positions
+    fn encode_position(&self, position: Position) -> Gains {
+        self.encode_azimuth(position.azimuth())
+    }
+
+    /// Encodes a tone at an arbitrary azimuth, in degrees: 0 is center, positive is to the right,
+    /// and 180 is rear
+    fn encode_azimuth(&self, azimuth: f32) -> Gains;
+}
+
+/// Decodes a matrix's (left total, right total) into discrete channels, like a passive decoder
+/// without steering logic. Decoding the tones that a `MatrixEncoder` writes gives a baseline to
+/// compare active decoders against
+pub trait MatrixDecoder {
+    /// The short name used on the command line, which matches the encoder's name
+    fn name(&self) -> &str;
+
+    /// A human-readable description of the decoder
+    fn description(&self) -> &str;
+
+    /// The positions of the decoded channels, in the order that `decode` returns them
+    fn positions(&self) -> Vec<Position>;
+
+    /// Decodes one sample of left total and right total, as analytic signals, into each channel.
+    /// The real part of each channel is its sample
+    fn decode(&self, left_total: Complex<f32>, right_total: Complex<f32>) -> Vec<Complex<f32>>;
+}
+
+/// All built-in matrixes
+pub fn matrices() -> Vec<Box<dyn MatrixEncoder>> {
+    vec![
+        Box::new(DefaultMatrix),
+        Box::new(SqMatrix),
+        Box::new(QsMatrix),
+        Box::new(DolbySurroundMatrix),
+        Box::new(ProLogic2Matrix),
+        Box::new(UhjMatrix),
+    ]
+}
+
+/// Looks up a built-in matrix by its name
+pub fn find_matrix(name: &str) -> Option<Box<dyn MatrixEncoder>> {
+    matrices().into_iter().find(|matrix| matrix.name() == name)
+}
+
+/// Looks up a built-in matrix by its name, or reads a `CustomMatrix` from a path that ends in
+/// ".json"
+pub fn load_matrix(name: &str) -> Result<Box<dyn MatrixEncoder>> {
+    if name.ends_with(".json") {
+        return Ok(Box::new(CustomMatrix::read(Path::new(name))?));
+    }
+
+    find_matrix(name)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("Unknown matrix \"{}\"", name)))
+}
+
+/// All built-in passive decoders
+pub fn decoders() -> Vec<Box<dyn MatrixDecoder>> {
+    vec![
+        Box::new(SqDecoder),
+        Box::new(QsDecoder),
+        Box::new(DolbySurroundDecoder),
+        Box::new(UhjDecoder),
+    ]
+}
+
+/// Looks up a built-in passive decoder by its name
+pub fn find_decoder(name: &str) -> Option<Box<dyn MatrixDecoder>> {
+    decoders()
+        .into_iter()
+        .find(|decoder| decoder.name() == name)
+}
+
+/// Encodes an azimuth by constant-power panning it between the two nearest discrete channels,
+/// then combining each channel's (left total, right total) gains
+pub fn encode_discrete(azimuth: f32, channels: &[(f32, Gains)]) -> Gains {
+    let speakers: Vec<f32> = channels.iter().map(|(azimuth, _)| *azimuth).collect();
+    let gains = pan(azimuth, &speakers);
+
+    let mut left_total = Complex::new(0.0, 0.0);
+    let mut right_total = Complex::new(0.0, 0.0);
+    for (gain, (_, (channel_left_total, channel_right_total))) in gains.iter().zip(channels) {
+        left_total += channel_left_total * gain;
+        right_total += channel_right_total * gain;
+    }
+
+    (left_total, right_total)
+}
+
+/// Applies (left total, right total) gains to one sample of an analytic signal, such as from
+/// `HilbertTransformer::process`. The gains' angles shift every frequency in the signal, so this works
+/// with any waveform, not just a single tone
+pub fn apply_gains(gains: Gains, analytic: Complex<f32>) -> (f32, f32) {
+    let (left_total, right_total) = gains;
+    ((left_total * analytic).re, (right_total * analytic).re)
+}
+
+// Checks that gains are within rounding of published coefficients
+#[cfg(test)]
+fn assert_gains(gains: Gains, left_total: Complex<f32>, right_total: Complex<f32>) {
+    assert!(
+        (gains.0 - left_total).norm() < 0.001 && (gains.1 - right_total).norm() < 0.001,
+        "{:?} is not ({}, {})",
+        gains,
+        left_total,
+        right_total
+    );
+}
+
+// Encodes a position, decodes it, and checks each decoded channel's level
+#[cfg(test)]
+fn assert_decoded_levels(
+    encoder: &dyn MatrixEncoder,
+    decoder: &dyn MatrixDecoder,
+    position: Position,
+    levels: &[f32],
+) {
+    let (left_total, right_total) = encoder.encode_position(position);
+    let decoded: Vec<f32> = decoder
+        .decode(left_total, right_total)
+        .iter()
+        .map(|channel| channel.norm())
+        .collect();
+
+    assert_eq!(decoded.len(), levels.len());
+    for (decoded_level, level) in decoded.iter().zip(levels) {
+        assert!(
+            (decoded_level - level).abs() < 0.01,
+            "{} decodes to {:?}, not {:?}",
+            position.name(),
+            decoded,
+            levels
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+use rustfft::num_complex::Complex;
+
+use crate::{panning::pan, position::Position};
+
+use super::{Gains, MatrixEncoder};
+
+// sqrt(2/3) and sqrt(1/3)
+const SURROUND_MAJOR: f32 = 0.8165;
+const SURROUND_MINOR: f32 = 0.5774;
+
+// Azimuths of the discrete speakers: left, center, right, left surround, right surround
+const SPEAKERS: [f32; 5] = [-45.0, 0.0, 45.0, -110.0, 110.0];
+
+/// Dolby Pro Logic II (5-channel)
+pub struct ProLogic2Matrix;
+
+impl MatrixEncoder for ProLogic2Matrix {
+    fn name(&self) -> &str {
+        "pro_logic_2"
+    }
+
+    fn description(&self) -> &str {
+        "Dolby Pro Logic II (5-channel)"
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        let mut positions = Position::STANDARD.to_vec();
+        positions.push(Position::LeftSurround);
+        positions.push(Position::RightSurround);
+        positions
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        let gains = pan(azimuth, &SPEAKERS);
+
+        pro_logic_2_encode(
+            Complex::from_polar(gains[0], 0.0),
+            Complex::from_polar(gains[1], 0.0),
+            Complex::from_polar(gains[2], 0.0),
+            Complex::from_polar(gains[3], 0.0),
+            Complex::from_polar(gains[4], 0.0),
+        )
+    }
+}
+
+/// Encodes five discrete channels into Pro Logic II's (left total, right total). Center is mixed
+/// into both totals at -3 dB. The surrounds are mixed into both totals with asymmetric amplitudes,
+/// shifted -90° into left total and +90° into right total
+pub fn pro_logic_2_encode(
+    left: Complex<f32>,
+    center: Complex<f32>,
+    right: Complex<f32>,
+    left_surround: Complex<f32>,
+    right_surround: Complex<f32>,
+) -> Gains {
+    let minus_90 = Complex::from_polar(1.0, -FRAC_PI_2);
+    let plus_90 = Complex::from_polar(1.0, FRAC_PI_2);
+
+    let left_total = left
+        + center * FRAC_1_SQRT_2
+        + (left_surround * SURROUND_MAJOR + right_surround * SURROUND_MINOR) * minus_90;
+
+    let right_total = right
+        + center * FRAC_1_SQRT_2
+        + (left_surround * SURROUND_MINOR + right_surround * SURROUND_MAJOR) * plus_90;
+
+    (left_total, right_total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::assert_gains;
+
+    #[test]
+    fn encodes_published_coefficients() {
+        let gains = |position| ProLogic2Matrix.encode_position(position);
+
+        assert_gains(
+            gains(Position::LeftFront),
+            Complex::new(1.0, 0.0),
+            Complex::new(0.0, 0.0),
+        );
+        assert_gains(
+            gains(Position::Center),
+            Complex::new(0.707, 0.0),
+            Complex::new(0.707, 0.0),
+        );
+        assert_gains(
+            gains(Position::LeftSurround),
+            Complex::new(0.0, -0.8165),
+            Complex::new(0.0, 0.5774),
+        );
+        assert_gains(
+            gains(Position::RightSurround),
+            Complex::new(0.0, -0.5774),
+            Complex::new(0.0, 0.8165),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::f32::consts::FRAC_PI_2;
+
+use rustfft::num_complex::Complex;
+
+use crate::{panning::pan, position::Position};
+
+use super::{Gains, MatrixDecoder, MatrixEncoder, QUAD_SPEAKERS};
+
+// cos(22.5°) and sin(22.5°)
+const QS_MAJOR: f32 = 0.924;
+const QS_MINOR: f32 = 0.383;
+
+/// Sansui QS (Regular Matrix)
+pub struct QsMatrix;
+
+impl MatrixEncoder for QsMatrix {
+    fn name(&self) -> &str {
+        "qs"
+    }
+
+    fn description(&self) -> &str {
+        "Sansui QS (Regular Matrix)"
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        let gains = pan(azimuth, &QUAD_SPEAKERS);
+
+        qs_encode(
+            Complex::from_polar(gains[0], 0.0),
+            Complex::from_polar(gains[1], 0.0),
+            Complex::from_polar(gains[2], 0.0),
+            Complex::from_polar(gains[3], 0.0),
+        )
+    }
+}
+
+/// Encodes four discrete channels into QS's (left total, right total)
+pub fn qs_encode(
+    left_front: Complex<f32>,
+    right_front: Complex<f32>,
+    left_rear: Complex<f32>,
+    right_rear: Complex<f32>,
+) -> Gains {
+    let plus_90 = Complex::from_polar(1.0, FRAC_PI_2);
+    let minus_90 = Complex::from_polar(1.0, -FRAC_PI_2);
+
+    let left_total = left_front * QS_MAJOR
+        + right_front * QS_MINOR
+        + left_rear * plus_90 * QS_MAJOR
+        + right_rear * plus_90 * QS_MINOR;
+
+    let right_total = left_front * QS_MINOR
+        + right_front * QS_MAJOR
+        + left_rear * minus_90 * QS_MINOR
+        + right_rear * minus_90 * QS_MAJOR;
+
+    (left_total, right_total)
+}
+
+/// Sansui QS's basic decoder, without Vario-Matrix logic
+pub struct QsDecoder;
+
+impl MatrixDecoder for QsDecoder {
+    fn name(&self) -> &str {
+        "qs"
+    }
+
+    fn description(&self) -> &str {
+        "Sansui QS basic decoder, without Vario-Matrix"
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        vec![
+            Position::LeftFront,
+            Position::RightFront,
+            Position::LeftRear,
+            Position::RightRear,
+        ]
+    }
+
+    fn decode(&self, left_total: Complex<f32>, right_total: Complex<f32>) -> Vec<Complex<f32>> {
+        let (left_front, right_front, left_rear, right_rear) = qs_decode(left_total, right_total);
+        vec![left_front, right_front, left_rear, right_rear]
+    }
+}
+
+/// Decodes QS's (left total, right total) into (left front, right front, left rear, right rear).
+/// This is the transpose of `qs_encode`, with the rears' phase shifts reversed
+pub fn qs_decode(
+    left_total: Complex<f32>,
+    right_total: Complex<f32>,
+) -> (Complex<f32>, Complex<f32>, Complex<f32>, Complex<f32>) {
+    let plus_90 = Complex::from_polar(1.0, FRAC_PI_2);
+    let minus_90 = Complex::from_polar(1.0, -FRAC_PI_2);
+
+    let left_front = left_total * QS_MAJOR + right_total * QS_MINOR;
+    let right_front = left_total * QS_MINOR + right_total * QS_MAJOR;
+    let left_rear = left_total * minus_90 * QS_MAJOR + right_total * plus_90 * QS_MINOR;
+    let right_rear = left_total * minus_90 * QS_MINOR + right_total * plus_90 * QS_MAJOR;
+
+    (left_front, right_front, left_rear, right_rear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{assert_decoded_levels, assert_gains};
+
+    #[test]
+    fn encodes_published_coefficients() {
+        let gains = |position| QsMatrix.encode_position(position);
+
+        assert_gains(
+            gains(Position::LeftFront),
+            Complex::new(0.924, 0.0),
+            Complex::new(0.383, 0.0),
+        );
+        assert_gains(
+            gains(Position::RightFront),
+            Complex::new(0.383, 0.0),
+            Complex::new(0.924, 0.0),
+        );
+        assert_gains(
+            gains(Position::LeftRear),
+            Complex::new(0.0, 0.924),
+            Complex::new(0.0, -0.383),
+        );
+        assert_gains(
+            gains(Position::RightRear),
+            Complex::new(0.0, 0.383),
+            Complex::new(0.0, -0.924),
+        );
+    }
+
+    // Levels are left front, right front, left rear, right rear. A passive QS decoder only separates
+    // diagonal channels
+    #[test]
+    fn decodes_what_it_encodes() {
+        assert_decoded_levels(
+            &QsMatrix,
+            &QsDecoder,
+            Position::LeftFront,
+            &[1.0, 0.707, 0.707, 0.0],
+        );
+        assert_decoded_levels(
+            &QsMatrix,
+            &QsDecoder,
+            Position::RightRear,
+            &[0.0, 0.707, 0.707, 1.0],
+        );
+    }
+}
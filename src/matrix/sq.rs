@@ -0,0 +1,121 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use rustfft::num_complex::Complex;
+
+use crate::{panning::pan, position::Position};
+
+use super::{Gains, MatrixDecoder, MatrixEncoder, QUAD_SPEAKERS};
+
+/// CBS SQ (Stereo Quadraphonic)
+pub struct SqMatrix;
+
+impl MatrixEncoder for SqMatrix {
+    fn name(&self) -> &str {
+        "sq"
+    }
+
+    fn description(&self) -> &str {
+        "CBS SQ (Stereo Quadraphonic)"
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        let gains = pan(azimuth, &QUAD_SPEAKERS);
+
+        sq_encode(
+            Complex::from_polar(gains[0], 0.0),
+            Complex::from_polar(gains[1], 0.0),
+            Complex::from_polar(gains[2], 0.0),
+            Complex::from_polar(gains[3], 0.0),
+        )
+    }
+}
+
+/// Encodes four discrete channels into SQ's (left total, right total)
+pub fn sq_encode(
+    left_front: Complex<f32>,
+    right_front: Complex<f32>,
+    left_rear: Complex<f32>,
+    right_rear: Complex<f32>,
+) -> Gains {
+    let (left_back_amplitude, left_back_phase) = left_rear.to_polar();
+    let (right_back_amplitude, right_back_phase) = right_rear.to_polar();
+
+    let left_back_for_left_total =
+        Complex::from_polar(0.7 * left_back_amplitude, left_back_phase - FRAC_PI_2);
+    let right_back_for_left_total =
+        Complex::from_polar(0.7 * right_back_amplitude, right_back_phase);
+    let left_total = left_front + left_back_for_left_total + right_back_for_left_total;
+
+    let left_back_for_right_total =
+        Complex::from_polar(0.7 * left_back_amplitude, left_back_phase + PI);
+    let right_back_for_right_total =
+        Complex::from_polar(0.7 * right_back_amplitude, right_back_phase + FRAC_PI_2);
+    let right_total = right_front + left_back_for_right_total + right_back_for_right_total;
+
+    (left_total, right_total)
+}
+
+/// CBS SQ's basic decoder, without logic
+pub struct SqDecoder;
+
+impl MatrixDecoder for SqDecoder {
+    fn name(&self) -> &str {
+        "sq"
+    }
+
+    fn description(&self) -> &str {
+        "CBS SQ basic decoder, without logic"
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        vec![
+            Position::LeftFront,
+            Position::RightFront,
+            Position::LeftRear,
+            Position::RightRear,
+        ]
+    }
+
+    fn decode(&self, left_total: Complex<f32>, right_total: Complex<f32>) -> Vec<Complex<f32>> {
+        let (left_front, right_front, left_rear, right_rear) = sq_decode(left_total, right_total);
+        vec![left_front, right_front, left_rear, right_rear]
+    }
+}
+
+/// Decodes SQ's (left total, right total) into (left front, right front, left rear, right rear).
+/// The fronts are the totals, and each rear undoes the phase shifts that `sq_encode` applies
+pub fn sq_decode(
+    left_total: Complex<f32>,
+    right_total: Complex<f32>,
+) -> (Complex<f32>, Complex<f32>, Complex<f32>, Complex<f32>) {
+    let left_rear = left_total * Complex::from_polar(0.7, FRAC_PI_2)
+        + right_total * Complex::from_polar(0.7, PI);
+    let right_rear = left_total * Complex::from_polar(0.7, 0.0)
+        + right_total * Complex::from_polar(0.7, -FRAC_PI_2);
+
+    (left_total, right_total, left_rear, right_rear)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::assert_decoded_levels;
+
+    // Levels are left front, right front, left rear, right rear. A passive SQ decoder only
+    // separates diagonal channels
+    #[test]
+    fn decodes_what_it_encodes() {
+        assert_decoded_levels(
+            &SqMatrix,
+            &SqDecoder,
+            Position::LeftFront,
+            &[1.0, 0.0, 0.7, 0.7],
+        );
+        assert_decoded_levels(
+            &SqMatrix,
+            &SqDecoder,
+            Position::LeftRear,
+            &[0.7, 0.7, 0.98, 0.0],
+        );
+    }
+}
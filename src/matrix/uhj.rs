@@ -0,0 +1,137 @@
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2, SQRT_2};
+
+use rustfft::num_complex::Complex;
+
+use crate::position::Position;
+
+use super::{Gains, MatrixDecoder, MatrixEncoder};
+
+/// Two-channel Ambisonic UHJ, encoded from horizontal B-format
+pub struct UhjMatrix;
+
+impl MatrixEncoder for UhjMatrix {
+    fn name(&self) -> &str {
+        "uhj"
+    }
+
+    fn description(&self) -> &str {
+        "Ambisonic UHJ (2-channel, horizontal only)"
+    }
+
+    fn encode_azimuth(&self, azimuth: f32) -> Gains {
+        // B-format's azimuth is counter-clockwise, but this program's azimuth is clockwise
+        let angle = -azimuth.to_radians();
+
+        uhj_encode(
+            Complex::from_polar(FRAC_1_SQRT_2, 0.0),
+            Complex::from_polar(angle.cos(), 0.0),
+            Complex::from_polar(angle.sin(), 0.0),
+        )
+    }
+}
+
+/// Encodes horizontal B-format (W, X, Y) into UHJ's (left total, right total)
+pub fn uhj_encode(w: Complex<f32>, x: Complex<f32>, y: Complex<f32>) -> Gains {
+    let plus_90 = Complex::from_polar(1.0, FRAC_PI_2);
+
+    let sum = w * 0.9396926 + x * 0.185574;
+    let difference = (w * -0.3420201 + x * 0.5098604) * plus_90 + y * 0.6554516;
+
+    ((sum + difference) * 0.5, (sum - difference) * 0.5)
+}
+
+/// Decodes two-channel UHJ to horizontal B-format, and then to a square of four speakers
+pub struct UhjDecoder;
+
+impl MatrixDecoder for UhjDecoder {
+    fn name(&self) -> &str {
+        "uhj"
+    }
+
+    fn description(&self) -> &str {
+        "Ambisonic UHJ (2-channel), decoded to a square of four speakers"
+    }
+
+    fn positions(&self) -> Vec<Position> {
+        vec![
+            Position::LeftFront,
+            Position::RightFront,
+            Position::LeftRear,
+            Position::RightRear,
+        ]
+    }
+
+    fn decode(&self, left_total: Complex<f32>, right_total: Complex<f32>) -> Vec<Complex<f32>> {
+        let (w, x, y) = uhj_decode(left_total, right_total);
+
+        self.positions()
+            .into_iter()
+            .map(|position| {
+                // B-format's azimuth is counter-clockwise, but this program's azimuth is clockwise
+                let angle = -position.azimuth().to_radians();
+                (w * SQRT_2 + x * angle.cos() + y * angle.sin()) * 0.5
+            })
+            .collect()
+    }
+}
+
+/// Decodes UHJ's (left total, right total) into horizontal B-format (W, X, Y), with Gerzon's
+/// two-channel decoding equations. Two channels can not carry all of B-format, so sounds are less
+/// directional after decoding
+pub fn uhj_decode(
+    left_total: Complex<f32>,
+    right_total: Complex<f32>,
+) -> (Complex<f32>, Complex<f32>, Complex<f32>) {
+    let plus_90 = Complex::from_polar(1.0, FRAC_PI_2);
+
+    let sum = left_total + right_total;
+    let difference = left_total - right_total;
+
+    let w = sum * 0.982 + difference * plus_90 * 0.197 * 0.828;
+    let x = sum * 0.419 - difference * plus_90 * 0.828;
+    let y = difference * 0.796 + sum * plus_90 * 0.187;
+
+    (w, x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{assert_decoded_levels, assert_gains};
+
+    // Expected values are from Gerzon's encoding equations: S = 0.9397 W + 0.1856 X,
+    // D = j(-0.3420 W + 0.5099 X) + 0.6555 Y, left total = (S + D) / 2, right total = (S - D) / 2
+    #[test]
+    fn encodes_published_coefficients() {
+        let gains = |position| UhjMatrix.encode_position(position);
+
+        assert_gains(
+            gains(Position::Center),
+            Complex::new(0.4250, 0.1340),
+            Complex::new(0.4250, -0.1340),
+        );
+        assert_gains(
+            gains(Position::LeftMiddle),
+            Complex::new(0.6600, -0.1209),
+            Complex::new(0.0045, 0.1209),
+        );
+        assert_gains(
+            gains(Position::RearCenter),
+            Complex::new(0.2394, -0.3759),
+            Complex::new(0.2394, 0.3759),
+        );
+    }
+
+    // Levels are left front, right front, left rear, right rear. Decoding B-format directly would
+    // give 1, 0.5, 0.5, and 0, but two channels can't carry all of B-format, so the opposite speaker
+    // is quieter, but not silent
+    #[test]
+    fn decodes_what_it_encodes() {
+        assert_decoded_levels(
+            &UhjMatrix,
+            &UhjDecoder,
+            Position::LeftFront,
+            &[0.822, 0.586, 0.585, 0.276],
+        );
+    }
+}
@@ -0,0 +1,987 @@
+use std::path::PathBuf;
+
+use soft_matrix_test_tones::{
+    sequence::azimuths_around_circle, Fade, FadeShape, FrequencyMode, NoiseColor, OutputFormat,
+    Signal,
+};
+use wave_stream::wave_header::SampleFormat;
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+pub const DEFAULT_FREQUENCY: f32 = 882.0;
+pub const DEFAULT_NOISE_BAND: (f32, f32) = (20.0, 20000.0);
+pub const DEFAULT_SEED: u64 = 1;
+pub const DEFAULT_TONE_DURATION: f32 = 0.25;
+pub const DEFAULT_SILENCE_DURATION: f32 = 0.025;
+pub const DEFAULT_MIN_SEPARATION: f32 = 20.0;
+
+const USAGE: &str = "\
+Generates test tones for use with soft_matrix
+
+Usage:
+    soft_matrix_test_tones [generate] [OPTIONS]
+    soft_matrix_test_tones encode --matrix <NAME> [OPTIONS] <INPUT>
+    soft_matrix_test_tones decode --matrix <NAME> [OPTIONS] <INPUT>
+    soft_matrix_test_tones analyze --matrix <NAME> [OPTIONS] <INPUT>
+    soft_matrix_test_tones render [OPTIONS] <PROGRAM>
+    soft_matrix_test_tones list-matrices
+    soft_matrix_test_tones describe <MATRIX>
+    soft_matrix_test_tones help
+
+Wherever a matrix is named, it can also be a .json file that defines a custom matrix with (left
+total, right total) coefficients for each discrete channel. See matrices/homebrew.json
+
+generate and render also write a .manifest.json file next to each wav, which lists each signal's
+start sample, length, position, azimuth, matrix, and exact (left total, right total) gains. A
+panning sweep's azimuth and gains change with every sample, so they aren't listed
+
+Generate options:
+    -m, --matrix <NAME>           Matrix to generate tones for. May be repeated. Defaults to all matrixes
+    -d, --output-dir <DIR>        Directory to write files into. Defaults to the current directory
+    -o, --output <FILE>           File name to write. Only valid when a single matrix is selected
+    -r, --sample-rate <HZ>        Sample rate, such as 44100, 48000, 88200, 96000, or 192000. Frequencies
+                                  and durations are the same at every sample rate. Defaults to 44100
+    -f, --frequency <LIST>        Comma-separated tone frequencies, in Hz. Each must be below half the
+                                  sample rate. Each position is written once per frequency. Defaults to 882
+        --multitone               Write all frequencies at the same time instead of one after another
+        --log-sweep <START,END>   Write an exponential sine sweep from START to END Hz at each position
+                                  instead of a tone. The sweep lasts as long as a tone
+        --noise <COLOR>           Write white or pink noise at each position instead of a tone
+        --noise-band <LOW,HIGH>   The noise's frequency range, in Hz. Defaults to 20,20000, limited to
+                                  below half the sample rate
+        --seed <N>                Seed for the noise. The same seed always writes the same noise.
+                                  Defaults to 1
+        --tone-duration <SECONDS> How long each tone lasts. Defaults to 0.25
+        --silence-duration <SECONDS>
+                                  How long the silence between tones lasts. Defaults to 0.025
+        --fade <SECONDS>          How long each tone fades in and out, so that it doesn't click when it
+                                  starts and stops. 0 disables fading. Defaults to 0.005
+        --fade-shape <SHAPE>      The fade's shape: raised-cosine or blackman. Defaults to raised-cosine
+        --azimuths <LIST>         Comma-separated azimuths, in degrees, to write tones at instead of the
+                                  matrix's positions. 0 is center, positive is right, 180 is rear
+        --azimuth-step <DEGREES>  Write tones every DEGREES around the circle instead of at the matrix's
+                                  positions
+        --panning-sweep <SECONDS> Write a tone that pans once around the circle over SECONDS instead of
+                                  stationary tones. The tone starts at center and moves to the right
+        --if-exists <POLICY>      What to do when an output file exists: overwrite, skip, or fail. Defaults to overwrite
+        --format <FORMAT>         Sample format to write: int16, int24, or float32. Defaults to float32
+        --dither                  Add TPDF dither when writing int16 or int24
+    -h, --help                    Prints this message
+
+Encode and decode options:
+    -m, --matrix <NAME>           Matrix to encode a discrete quad or 5.1 wav with, or to decode a stereo
+                                  wav with a passive decoder. Decoding supports sq, qs, dolby_surround,
+                                  and uhj. LFE is not encoded
+    -o, --output <FILE>           File to write. Defaults to the input's name followed by _<NAME>.wav
+                                  when encoding, or _<NAME>_decoded.wav when decoding
+        --if-exists <POLICY>      What to do when the output file exists: overwrite, skip, or fail.
+                                  Defaults to overwrite
+        --format <FORMAT>         Sample format to write: int16, int24, or float32. Defaults to float32
+        --dither                  Add TPDF dither when writing int16 or int24
+
+Render options:
+    <PROGRAM>                     A JSON file that lists the segments to write, in order. Each segment
+                                  is a silence, tone, sweep, or noise, with its duration in seconds,
+                                  and each signal has a matrix, a position or azimuth, and a level
+    -o, --output <FILE>           File to write. Defaults to the program's name, ending in .wav
+    -r, --sample-rate <HZ>        Sample rate. Defaults to 44100
+        --fade <SECONDS>          How long each signal fades in and out. Defaults to 0.005
+        --fade-shape <SHAPE>      The fade's shape: raised-cosine or blackman. Defaults to raised-cosine
+        --if-exists <POLICY>      What to do when the output file exists: overwrite, skip, or fail.
+                                  Defaults to overwrite
+        --format <FORMAT>         Sample format to write: int16, int24, or float32. Defaults to float32
+        --dither                  Add TPDF dither when writing int16 or int24
+
+Analyze options:
+    -m, --matrix <NAME>           Matrix that the wav was generated for
+    -f, --frequency <LIST>        The tone frequencies that the wav was generated with. Defaults to 882
+        --multitone               The wav was generated with all frequencies at the same time
+        --tone-duration <SECONDS> The tone duration that the wav was generated with. Defaults to 0.25
+        --silence-duration <SECONDS>
+                                  The silence duration that the wav was generated with. Defaults to 0.025
+        --fade <SECONDS>          The fade that the wav was generated with, which the latency is
+                                  corrected for. Defaults to 0.005
+        --fade-shape <SHAPE>      The fade shape that the wav was generated with. Defaults to
+                                  raised-cosine
+        --azimuths <LIST>         The azimuths that the wav was generated with
+        --azimuth-step <DEGREES>  The azimuth step that the wav was generated with
+        --min-separation <DB>     When analyzing a decoded wav with more than two channels, the minimum
+                                  separation between the channels that a position is decoded to and
+                                  the loudest other channel. Exits with an error if any position fails.
+                                  Defaults to 20
+        --latency <SAMPLES>       How many samples the decoder delayed the decoded wav by. Defaults to
+                                  finding it from the start of the first tone
+";
+
+pub enum Command {
+    Generate(GenerateOptions),
+    Encode(ConvertOptions),
+    Decode(ConvertOptions),
+    Analyze(AnalyzeOptions),
+    Render(RenderOptions),
+    ListMatrices,
+    Describe(String),
+    Help,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum IfExists {
+    Overwrite,
+    Skip,
+    Fail,
+}
+
+pub struct GenerateOptions {
+    // Empty means "all matrixes"
+    pub matrices: Vec<String>,
+    pub output_dir: PathBuf,
+    pub output_file: Option<PathBuf>,
+    pub sample_rate: u32,
+    // None means DEFAULT_FREQUENCY
+    pub frequencies: Option<Vec<f32>>,
+    pub multitone: bool,
+    // Some means "write a sweep from start to end frequency instead of tones"
+    pub log_sweep: Option<(f32, f32)>,
+    // Some means "write noise instead of tones"
+    pub noise: Option<NoiseColor>,
+    // None means DEFAULT_NOISE_BAND
+    pub noise_band: Option<(f32, f32)>,
+    pub seed: u64,
+    // Durations are in seconds
+    pub tone_duration: f32,
+    pub silence_duration: f32,
+    pub fade: Fade,
+    // None means "the matrix's positions"
+    pub azimuths: Option<Vec<f32>>,
+    // Some means "write a panning sweep that lasts this many seconds"
+    pub sweep_duration: Option<f32>,
+    pub if_exists: IfExists,
+    pub output_format: OutputFormat,
+}
+
+// Options for commands that convert one wav file into another with a matrix
+pub struct ConvertOptions {
+    pub matrix: String,
+    pub input: PathBuf,
+    // None means "next to the input"
+    pub output: Option<PathBuf>,
+    pub if_exists: IfExists,
+    pub output_format: OutputFormat,
+}
+
+impl ConvertOptions {
+    /// The file that the converted wav is written to. Defaults to the input's name followed by
+    /// `_<suffix>.wav`
+    pub fn output(&self, suffix: &str) -> PathBuf {
+        match &self.output {
+            Some(output) => output.clone(),
+            None => {
+                let stem = self
+                    .input
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                self.input
+                    .with_file_name(format!("{}_{}.wav", stem, suffix))
+            }
+        }
+    }
+}
+
+pub struct RenderOptions {
+    pub program: PathBuf,
+    // None means "next to the program"
+    pub output: Option<PathBuf>,
+    pub sample_rate: u32,
+    pub fade: Fade,
+    pub if_exists: IfExists,
+    pub output_format: OutputFormat,
+}
+
+impl RenderOptions {
+    /// The file that the program is written to
+    pub fn output(&self) -> PathBuf {
+        match &self.output {
+            Some(output) => output.clone(),
+            None => self.program.with_extension("wav"),
+        }
+    }
+}
+
+// Describes the layout of a generated wav, so that its tones can be found
+pub struct AnalyzeOptions {
+    pub matrix: String,
+    pub input: PathBuf,
+    // None means DEFAULT_FREQUENCY
+    pub frequencies: Option<Vec<f32>>,
+    pub multitone: bool,
+    // Durations are in seconds
+    pub tone_duration: f32,
+    pub silence_duration: f32,
+    // None means "the matrix's positions"
+    pub azimuths: Option<Vec<f32>>,
+    pub fade: Fade,
+    pub min_separation: f32,
+    // None means "find the latency from the start of the first tone"
+    pub latency: Option<usize>,
+}
+
+impl AnalyzeOptions {
+    /// The signals that the wav was generated with at each position
+    pub fn signals(&self) -> Vec<Signal> {
+        let frequencies = self
+            .frequencies
+            .clone()
+            .unwrap_or_else(|| vec![DEFAULT_FREQUENCY]);
+
+        if self.multitone {
+            FrequencyMode::Multitone.signals(frequencies)
+        } else {
+            FrequencyMode::Sequential.signals(frequencies)
+        }
+    }
+}
+
+impl GenerateOptions {
+    /// The signals written at each position
+    pub fn signals(&self) -> Vec<Signal> {
+        if let Some(color) = self.noise {
+            let (low_frequency, high_frequency) = self.noise_band.unwrap_or(DEFAULT_NOISE_BAND);
+
+            return vec![Signal::Noise {
+                color,
+                low_frequency,
+                high_frequency: high_frequency.min(self.sample_rate as f32 / 2.0),
+                seed: self.seed,
+            }];
+        }
+
+        if let Some((start_frequency, end_frequency)) = self.log_sweep {
+            return vec![Signal::Sweep {
+                start_frequency,
+                end_frequency,
+            }];
+        }
+
+        let frequencies = self
+            .frequencies
+            .clone()
+            .unwrap_or_else(|| vec![DEFAULT_FREQUENCY]);
+
+        if self.multitone {
+            FrequencyMode::Multitone.signals(frequencies)
+        } else {
+            FrequencyMode::Sequential.signals(frequencies)
+        }
+    }
+}
+
+pub fn usage() -> &'static str {
+    USAGE
+}
+
+impl Command {
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Command, String> {
+        let mut args = args.skip(1).peekable();
+
+        let command = match args.peek().map(|arg| arg.as_str()) {
+            Some("generate") => {
+                args.next();
+                "generate"
+            }
+            Some("encode") => {
+                args.next();
+                "encode"
+            }
+            Some("decode") => {
+                args.next();
+                "decode"
+            }
+            Some("analyze") => {
+                args.next();
+                "analyze"
+            }
+            Some("render") => {
+                args.next();
+                "render"
+            }
+            Some("list-matrices") => {
+                args.next();
+                "list-matrices"
+            }
+            Some("describe") => {
+                args.next();
+                "describe"
+            }
+            Some("help") => {
+                args.next();
+                "help"
+            }
+            _ => "generate",
+        };
+
+        match command {
+            "list-matrices" => {
+                expect_no_more_args(args)?;
+                Ok(Command::ListMatrices)
+            }
+            "describe" => {
+                let matrix = match args.next() {
+                    Some(arg) if arg == "-h" || arg == "--help" => return Ok(Command::Help),
+                    Some(matrix) => matrix,
+                    None => return Err("describe requires the name of a matrix".to_string()),
+                };
+                expect_no_more_args(args)?;
+                Ok(Command::Describe(matrix))
+            }
+            "help" => Ok(Command::Help),
+            "encode" => parse_convert("encode", Command::Encode, args),
+            "decode" => parse_convert("decode", Command::Decode, args),
+            "analyze" => parse_analyze(args),
+            "render" => parse_render(args),
+            _ => parse_generate(args),
+        }
+    }
+}
+
+fn parse_convert(
+    command: &str,
+    to_command: fn(ConvertOptions) -> Command,
+    mut args: impl Iterator<Item = String>,
+) -> Result<Command, String> {
+    let mut matrix = None;
+    let mut input = None;
+    let mut output = None;
+    let mut if_exists = IfExists::Overwrite;
+    let mut output_format = OutputFormat::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-m" | "--matrix" => {
+                if matrix.is_some() {
+                    return Err(format!("{} only supports one --matrix", command));
+                }
+                matrix = Some(value_for(&arg, &mut args)?)
+            }
+            "-o" | "--output" => output = Some(PathBuf::from(value_for(&arg, &mut args)?)),
+            "--if-exists" => if_exists = parse_if_exists(&arg, &mut args)?,
+            "--format" => output_format.sample_format = parse_sample_format(&arg, &mut args)?,
+            "--dither" => output_format.dither = true,
+            other if other.starts_with('-') => return Err(format!("Unknown argument: {}", other)),
+            other => {
+                if input.is_some() {
+                    return Err(format!("Unexpected argument: {}", other));
+                }
+                input = Some(PathBuf::from(other))
+            }
+        }
+    }
+
+    validate_output_format(output_format)?;
+
+    Ok(to_command(ConvertOptions {
+        matrix: matrix.ok_or_else(|| format!("{} requires --matrix", command))?,
+        input: input.ok_or_else(|| format!("{} requires an input file", command))?,
+        output,
+        if_exists,
+        output_format,
+    }))
+}
+
+fn parse_render(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut program = None;
+    let mut options = RenderOptions {
+        program: PathBuf::new(),
+        output: None,
+        sample_rate: DEFAULT_SAMPLE_RATE,
+        fade: Fade::default(),
+        if_exists: IfExists::Overwrite,
+        output_format: OutputFormat::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-o" | "--output" => options.output = Some(PathBuf::from(value_for(&arg, &mut args)?)),
+            "-r" | "--sample-rate" => options.sample_rate = parse_value(&arg, &mut args)?,
+            "--fade" => options.fade.duration = parse_value(&arg, &mut args)?,
+            "--fade-shape" => options.fade.shape = parse_fade_shape(&arg, &mut args)?,
+            "--if-exists" => options.if_exists = parse_if_exists(&arg, &mut args)?,
+            "--format" => {
+                options.output_format.sample_format = parse_sample_format(&arg, &mut args)?
+            }
+            "--dither" => options.output_format.dither = true,
+            other if other.starts_with('-') => return Err(format!("Unknown argument: {}", other)),
+            other => {
+                if program.is_some() {
+                    return Err(format!("Unexpected argument: {}", other));
+                }
+                program = Some(PathBuf::from(other))
+            }
+        }
+    }
+
+    options.program = program.ok_or_else(|| "render requires a program file".to_string())?;
+
+    if options.sample_rate == 0 {
+        return Err("The sample rate must be greater than 0".to_string());
+    }
+
+    validate_fade(options.fade)?;
+    validate_output_format(options.output_format)?;
+
+    Ok(Command::Render(options))
+}
+
+fn parse_analyze(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut matrix = None;
+    let mut input = None;
+    let mut options = AnalyzeOptions {
+        matrix: String::new(),
+        input: PathBuf::new(),
+        frequencies: None,
+        multitone: false,
+        tone_duration: DEFAULT_TONE_DURATION,
+        silence_duration: DEFAULT_SILENCE_DURATION,
+        azimuths: None,
+        fade: Fade::default(),
+        min_separation: DEFAULT_MIN_SEPARATION,
+        latency: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--min-separation" => options.min_separation = parse_value(&arg, &mut args)?,
+            "--latency" => options.latency = Some(parse_value(&arg, &mut args)?),
+            "--fade" => options.fade.duration = parse_value(&arg, &mut args)?,
+            "--fade-shape" => options.fade.shape = parse_fade_shape(&arg, &mut args)?,
+            "-m" | "--matrix" => {
+                if matrix.is_some() {
+                    return Err("analyze only supports one --matrix".to_string());
+                }
+                matrix = Some(value_for(&arg, &mut args)?)
+            }
+            "-f" | "--frequency" => options.frequencies = Some(parse_list(&arg, &mut args)?),
+            "--multitone" => options.multitone = true,
+            "--tone-duration" => options.tone_duration = parse_value(&arg, &mut args)?,
+            "--silence-duration" => options.silence_duration = parse_value(&arg, &mut args)?,
+            "--azimuths" => {
+                if options.azimuths.is_some() {
+                    return Err("Only one of --azimuths or --azimuth-step may be used".to_string());
+                }
+
+                options.azimuths = Some(parse_list(&arg, &mut args)?);
+            }
+            "--azimuth-step" => {
+                if options.azimuths.is_some() {
+                    return Err("Only one of --azimuths or --azimuth-step may be used".to_string());
+                }
+
+                options.azimuths = Some(parse_azimuth_step(&arg, &mut args)?);
+            }
+            other if other.starts_with('-') => return Err(format!("Unknown argument: {}", other)),
+            other => {
+                if input.is_some() {
+                    return Err(format!("Unexpected argument: {}", other));
+                }
+                input = Some(PathBuf::from(other))
+            }
+        }
+    }
+
+    options.matrix = matrix.ok_or_else(|| "analyze requires --matrix".to_string())?;
+    options.input = input.ok_or_else(|| "analyze requires an input file".to_string())?;
+
+    if let Some(frequencies) = &options.frequencies {
+        if frequencies.is_empty() {
+            return Err("--frequency must list at least one frequency".to_string());
+        }
+
+        if frequencies
+            .iter()
+            .any(|frequency| !frequency.is_finite() || *frequency <= 0.0)
+        {
+            return Err("Frequencies must be greater than 0".to_string());
+        }
+    }
+
+    validate_durations(options.tone_duration, options.silence_duration)?;
+
+    if let Some(azimuths) = &options.azimuths {
+        if azimuths.is_empty() || azimuths.iter().any(|azimuth| !azimuth.is_finite()) {
+            return Err("--azimuths must list at least one finite azimuth".to_string());
+        }
+    }
+
+    validate_fade(options.fade)?;
+
+    if !options.min_separation.is_finite() {
+        return Err("--min-separation must be a finite number".to_string());
+    }
+
+    Ok(Command::Analyze(options))
+}
+
+fn parse_generate(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = GenerateOptions {
+        matrices: Vec::new(),
+        output_dir: PathBuf::from("."),
+        output_file: None,
+        sample_rate: DEFAULT_SAMPLE_RATE,
+        frequencies: None,
+        multitone: false,
+        log_sweep: None,
+        noise: None,
+        noise_band: None,
+        seed: DEFAULT_SEED,
+        tone_duration: DEFAULT_TONE_DURATION,
+        silence_duration: DEFAULT_SILENCE_DURATION,
+        fade: Fade::default(),
+        azimuths: None,
+        sweep_duration: None,
+        if_exists: IfExists::Overwrite,
+        output_format: OutputFormat::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-m" | "--matrix" => options.matrices.push(value_for(&arg, &mut args)?),
+            "-d" | "--output-dir" => {
+                options.output_dir = PathBuf::from(value_for(&arg, &mut args)?)
+            }
+            "-o" | "--output" => {
+                options.output_file = Some(PathBuf::from(value_for(&arg, &mut args)?))
+            }
+            "-r" | "--sample-rate" => options.sample_rate = parse_value(&arg, &mut args)?,
+            "-f" | "--frequency" => options.frequencies = Some(parse_list(&arg, &mut args)?),
+            "--multitone" => options.multitone = true,
+            "--log-sweep" => {
+                let frequencies = parse_list(&arg, &mut args)?;
+                if frequencies.len() != 2 {
+                    return Err("--log-sweep requires a start and end frequency".to_string());
+                }
+                options.log_sweep = Some((frequencies[0], frequencies[1]));
+            }
+            "--noise" => {
+                options.noise = match value_for(&arg, &mut args)?.as_str() {
+                    "white" => Some(NoiseColor::White),
+                    "pink" => Some(NoiseColor::Pink),
+                    other => {
+                        return Err(format!(
+                            "Unknown noise color \"{}\", expected white or pink",
+                            other
+                        ))
+                    }
+                }
+            }
+            "--noise-band" => {
+                let frequencies = parse_list(&arg, &mut args)?;
+                if frequencies.len() != 2 {
+                    return Err("--noise-band requires a low and high frequency".to_string());
+                }
+                options.noise_band = Some((frequencies[0], frequencies[1]));
+            }
+            "--seed" => options.seed = parse_value(&arg, &mut args)?,
+            "--tone-duration" => options.tone_duration = parse_value(&arg, &mut args)?,
+            "--silence-duration" => options.silence_duration = parse_value(&arg, &mut args)?,
+            "--azimuths" => {
+                if options.azimuths.is_some() {
+                    return Err("Only one of --azimuths or --azimuth-step may be used".to_string());
+                }
+
+                options.azimuths = Some(parse_list(&arg, &mut args)?);
+            }
+            "--azimuth-step" => {
+                if options.azimuths.is_some() {
+                    return Err("Only one of --azimuths or --azimuth-step may be used".to_string());
+                }
+
+                options.azimuths = Some(parse_azimuth_step(&arg, &mut args)?);
+            }
+            "--fade" => options.fade.duration = parse_value(&arg, &mut args)?,
+            "--fade-shape" => options.fade.shape = parse_fade_shape(&arg, &mut args)?,
+            "--panning-sweep" => options.sweep_duration = Some(parse_value(&arg, &mut args)?),
+            "--if-exists" => options.if_exists = parse_if_exists(&arg, &mut args)?,
+            "--format" => {
+                options.output_format.sample_format = parse_sample_format(&arg, &mut args)?
+            }
+            "--dither" => options.output_format.dither = true,
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    validate(&options)?;
+
+    Ok(Command::Generate(options))
+}
+
+fn validate(options: &GenerateOptions) -> Result<(), String> {
+    if options.sample_rate == 0 {
+        return Err("The sample rate must be greater than 0".to_string());
+    }
+
+    if options.log_sweep.is_some() && (options.frequencies.is_some() || options.multitone) {
+        return Err("--log-sweep can not be combined with --frequency or --multitone".to_string());
+    }
+
+    if options.noise.is_some()
+        && (options.frequencies.is_some() || options.multitone || options.log_sweep.is_some())
+    {
+        return Err(
+            "--noise can not be combined with --frequency, --multitone, or --log-sweep".to_string(),
+        );
+    }
+
+    if options.noise_band.is_some() && options.noise.is_none() {
+        return Err("--noise-band requires --noise".to_string());
+    }
+
+    if let Some((low_frequency, high_frequency)) = options.noise_band {
+        if !(low_frequency >= 0.0 && low_frequency < high_frequency) {
+            return Err(
+                "--noise-band's low frequency must be at least 0 and below its high frequency"
+                    .to_string(),
+            );
+        }
+
+        if low_frequency >= options.sample_rate as f32 / 2.0 {
+            return Err(format!(
+                "--noise-band's low frequency ({} Hz) must be below half the sample rate ({} Hz)",
+                low_frequency,
+                options.sample_rate as f32 / 2.0
+            ));
+        }
+    }
+
+    let frequencies = match (&options.frequencies, options.log_sweep) {
+        (Some(frequencies), _) => frequencies.clone(),
+        (None, Some((start_frequency, end_frequency))) => {
+            if start_frequency == end_frequency {
+                return Err("--log-sweep's start and end frequencies must differ".to_string());
+            }
+
+            vec![start_frequency, end_frequency]
+        }
+        (None, None) => vec![DEFAULT_FREQUENCY],
+    };
+
+    if frequencies.is_empty() {
+        return Err("--frequency must list at least one frequency".to_string());
+    }
+
+    for frequency in frequencies.iter() {
+        if !frequency.is_finite() || *frequency <= 0.0 {
+            return Err("Frequencies must be greater than 0".to_string());
+        }
+
+        if *frequency >= options.sample_rate as f32 / 2.0 {
+            return Err(format!(
+                "The frequency ({} Hz) must be below half the sample rate ({} Hz)",
+                frequency,
+                options.sample_rate as f32 / 2.0
+            ));
+        }
+    }
+
+    validate_durations(options.tone_duration, options.silence_duration)?;
+
+    validate_fade(options.fade)?;
+
+    // A panning sweep is one long tone, so it's faded in and out instead of each tone
+    let (faded_flag, faded_duration) = match options.sweep_duration {
+        Some(sweep_duration) => ("--panning-sweep", sweep_duration),
+        None => ("--tone-duration", options.tone_duration),
+    };
+    if options.fade.duration > faded_duration / 2.0 {
+        return Err(format!(
+            "--fade ({} seconds) can be at most half of {} ({} seconds)",
+            options.fade.duration, faded_flag, faded_duration
+        ));
+    }
+
+    if let Some(azimuths) = &options.azimuths {
+        if azimuths.is_empty() {
+            return Err("--azimuths must list at least one azimuth".to_string());
+        }
+
+        if azimuths.iter().any(|azimuth| !azimuth.is_finite()) {
+            return Err("--azimuths must be finite numbers".to_string());
+        }
+    }
+
+    if let Some(sweep_duration) = options.sweep_duration {
+        if !(sweep_duration.is_finite() && sweep_duration > 0.0) {
+            return Err("--panning-sweep must be greater than 0".to_string());
+        }
+    }
+
+    if options.sweep_duration.is_some() && options.azimuths.is_some() {
+        return Err(
+            "--panning-sweep can not be combined with --azimuths or --azimuth-step".to_string(),
+        );
+    }
+
+    validate_output_format(options.output_format)?;
+
+    if options.output_file.is_some() && options.matrices.len() != 1 {
+        return Err("--output can only be used when exactly one --matrix is selected".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_durations(tone_duration: f32, silence_duration: f32) -> Result<(), String> {
+    if !(tone_duration.is_finite() && tone_duration > 0.0) {
+        return Err("--tone-duration must be greater than 0".to_string());
+    }
+
+    if !(silence_duration.is_finite() && silence_duration >= 0.0) {
+        return Err("--silence-duration must be at least 0".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_fade(fade: Fade) -> Result<(), String> {
+    if !(fade.duration.is_finite() && fade.duration >= 0.0) {
+        return Err("--fade must be at least 0".to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_output_format(output_format: OutputFormat) -> Result<(), String> {
+    if output_format.dither && output_format.sample_format == SampleFormat::Float {
+        return Err("--dither requires --format int16 or int24".to_string());
+    }
+
+    Ok(())
+}
+
+fn expect_no_more_args(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    match args.next() {
+        Some(arg) => Err(format!("Unexpected argument: {}", arg)),
+        None => Ok(()),
+    }
+}
+
+fn value_for(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+fn parse_if_exists(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<IfExists, String> {
+    match value_for(flag, args)?.as_str() {
+        "overwrite" => Ok(IfExists::Overwrite),
+        "skip" => Ok(IfExists::Skip),
+        "fail" => Ok(IfExists::Fail),
+        other => Err(format!(
+            "Unknown {} policy \"{}\", expected overwrite, skip, or fail",
+            flag, other
+        )),
+    }
+}
+
+fn parse_sample_format(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<SampleFormat, String> {
+    match value_for(flag, args)?.as_str() {
+        "int16" => Ok(SampleFormat::Int16),
+        "int24" => Ok(SampleFormat::Int24),
+        "float32" => Ok(SampleFormat::Float),
+        other => Err(format!(
+            "Unknown {} \"{}\", expected int16, int24, or float32",
+            flag, other
+        )),
+    }
+}
+
+fn parse_fade_shape(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<FadeShape, String> {
+    match value_for(flag, args)?.as_str() {
+        "raised-cosine" => Ok(FadeShape::RaisedCosine),
+        "blackman" => Ok(FadeShape::Blackman),
+        other => Err(format!(
+            "Unknown {} \"{}\", expected raised-cosine or blackman",
+            flag, other
+        )),
+    }
+}
+
+fn parse_azimuth_step(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<Vec<f32>, String> {
+    let step: f32 = parse_value(flag, args)?;
+    if !(step > 0.0 && step <= 360.0) {
+        return Err(format!(
+            "{} must be greater than 0 and at most 360, got {}",
+            flag, step
+        ));
+    }
+
+    Ok(azimuths_around_circle(step))
+}
+
+fn parse_value<T: std::str::FromStr>(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<T, String> {
+    let value = value_for(flag, args)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+fn parse_list(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<Vec<f32>, String> {
+    let value = value_for(flag, args)?;
+    value
+        .split(',')
+        .map(|item| item.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, String> {
+        Command::parse(
+            ["soft_matrix_test_tones"]
+                .into_iter()
+                .chain(args.split_whitespace())
+                .map(str::to_string),
+        )
+    }
+
+    fn parse_error(args: &str) -> String {
+        match parse(args) {
+            Ok(_) => panic!("Expected \"{}\" to be rejected", args),
+            Err(err) => err,
+        }
+    }
+
+    fn parse_generate(args: &str) -> GenerateOptions {
+        match parse(args) {
+            Ok(Command::Generate(options)) => options,
+            Ok(_) => panic!("Expected \"{}\" to generate", args),
+            Err(err) => panic!("Expected \"{}\" to parse: {}", args, err),
+        }
+    }
+
+    #[test]
+    fn defaults_to_generate() {
+        let options = parse_generate("");
+        assert!(options.matrices.is_empty());
+        assert_eq!(options.sample_rate, DEFAULT_SAMPLE_RATE);
+        assert_eq!(options.fade, Fade::default());
+
+        let options = parse_generate("-m sq -o sq_test.wav");
+        assert_eq!(options.matrices, ["sq"]);
+        assert_eq!(options.output_file, Some(PathBuf::from("sq_test.wav")));
+
+        assert!(matches!(parse("generate"), Ok(Command::Generate(_))));
+    }
+
+    #[test]
+    fn output_requires_exactly_one_matrix() {
+        let expected = "--output can only be used when exactly one --matrix is selected";
+        assert_eq!(parse_error("-o out.wav"), expected);
+        assert_eq!(parse_error("-m sq -m qs -o out.wav"), expected);
+    }
+
+    #[test]
+    fn rejects_conflicting_signals() {
+        assert_eq!(
+            parse_error("--log-sweep 20,20000 --frequency 1000"),
+            "--log-sweep can not be combined with --frequency or --multitone"
+        );
+        assert_eq!(
+            parse_error("--noise-band 100,1000"),
+            "--noise-band requires --noise"
+        );
+        assert!(matches!(
+            parse("--noise pink --noise-band 100,1000"),
+            Ok(Command::Generate(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_frequencies_at_or_above_nyquist() {
+        assert_eq!(
+            parse_error("-r 44100 --frequency 22050"),
+            "The frequency (22050 Hz) must be below half the sample rate (22050 Hz)"
+        );
+        assert!(
+            parse_error("-r 48000 --log-sweep 20,30000").starts_with("The frequency (30000 Hz)")
+        );
+        assert!(matches!(
+            parse("-r 96000 --frequency 30000"),
+            Ok(Command::Generate(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_conflicting_placements() {
+        let expected = "Only one of --azimuths or --azimuth-step may be used";
+        assert_eq!(parse_error("--azimuths 0,90 --azimuth-step 30"), expected);
+        assert_eq!(parse_error("--azimuth-step 30 --azimuths 0,90"), expected);
+        assert_eq!(
+            parse_error("--panning-sweep 10 --azimuths 0,90"),
+            "--panning-sweep can not be combined with --azimuths or --azimuth-step"
+        );
+    }
+
+    #[test]
+    fn dither_requires_an_integer_format() {
+        assert_eq!(
+            parse_error("--dither"),
+            "--dither requires --format int16 or int24"
+        );
+        assert_eq!(
+            parse_error("--format float32 --dither"),
+            "--dither requires --format int16 or int24"
+        );
+
+        let options = parse_generate("--format int16 --dither");
+        assert_eq!(options.output_format.sample_format, SampleFormat::Int16);
+        assert!(options.output_format.dither);
+    }
+
+    #[test]
+    fn describe_requires_a_matrix() {
+        assert_eq!(
+            parse_error("describe"),
+            "describe requires the name of a matrix"
+        );
+        assert!(matches!(parse("describe sq"), Ok(Command::Describe(matrix)) if matrix == "sq"));
+        assert_eq!(parse_error("describe sq qs"), "Unexpected argument: qs");
+    }
+
+    #[test]
+    fn fade_is_limited_by_what_it_fades() {
+        assert_eq!(
+            parse_error("--fade 0.2"),
+            "--fade (0.2 seconds) can be at most half of --tone-duration (0.25 seconds)"
+        );
+        assert_eq!(
+            parse_generate("--panning-sweep 10 --fade 0.2")
+                .fade
+                .duration,
+            0.2
+        );
+        assert_eq!(
+            parse_error("--panning-sweep 0.2 --fade 0.2"),
+            "--fade (0.2 seconds) can be at most half of --panning-sweep (0.2 seconds)"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::f32::consts::FRAC_PI_2;
+
+/// Normalizes an azimuth, in degrees, to 0..360
+pub fn normalize_azimuth(azimuth: f32) -> f32 {
+    let azimuth = azimuth.rem_euclid(360.0);
+
+    // rem_euclid can round up to exactly 360
+    if azimuth >= 360.0 {
+        0.0
+    } else {
+        azimuth
+    }
+}
+
+/// Constant-power pans a source at `azimuth` between the two speakers on either side of it.
+/// Speaker azimuths are in degrees, in any order. Returns one gain per speaker
+pub fn pan(azimuth: f32, speakers: &[f32]) -> Vec<f32> {
+    let mut gains = vec![0.0; speakers.len()];
+
+    if speakers.len() == 1 {
+        gains[0] = 1.0;
+    }
+
+    if speakers.len() < 2 {
+        return gains;
+    }
+
+    let mut order: Vec<usize> = (0..speakers.len()).collect();
+    order.sort_by(|a, b| {
+        normalize_azimuth(speakers[*a]).total_cmp(&normalize_azimuth(speakers[*b]))
+    });
+
+    let azimuth = normalize_azimuth(azimuth);
+
+    for order_ctr in 0..order.len() {
+        let from = order[order_ctr];
+        let to = order[(order_ctr + 1) % order.len()];
+
+        let from_azimuth = normalize_azimuth(speakers[from]);
+        let span = normalize_azimuth(speakers[to] - from_azimuth);
+        let offset = normalize_azimuth(azimuth - from_azimuth);
+
+        // When speakers share an azimuth, the span is 0
+        if offset < span || (span == 0.0 && offset == 0.0) {
+            let fraction = if span == 0.0 { 0.0 } else { offset / span };
+            gains[from] = (fraction * FRAC_PI_2).cos();
+            gains[to] = (fraction * FRAC_PI_2).sin();
+            return gains;
+        }
+    }
+
+    // Only reachable when every speaker shares the same azimuth
+    gains[order[0]] = 1.0;
+    gains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_gains(gains: Vec<f32>, expected: &[f32]) {
+        assert_eq!(gains.len(), expected.len());
+        for (gain, expected_gain) in gains.iter().zip(expected) {
+            assert!(
+                (gain - expected_gain).abs() < 0.001,
+                "{:?} is not {:?}",
+                gains,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn pans_with_constant_power() {
+        let speakers = [-45.0, 45.0, -135.0, 135.0];
+        assert_gains(pan(0.0, &speakers), &[0.707, 0.707, 0.0, 0.0]);
+        assert_gains(pan(-45.0, &speakers), &[1.0, 0.0, 0.0, 0.0]);
+
+        // A quarter of the way from right front to right rear is cos and sin of 22.5°
+        assert_gains(pan(67.5, &speakers), &[0.0, 0.924, 0.0, 0.383]);
+    }
+
+    #[test]
+    fn wraps_through_the_rear() {
+        let speakers = [-45.0, 45.0, -135.0, 135.0];
+        assert_gains(pan(180.0, &speakers), &[0.0, 0.0, 0.707, 0.707]);
+        assert_gains(pan(-180.0, &speakers), &[0.0, 0.0, 0.707, 0.707]);
+        assert_gains(pan(157.5, &speakers), &[0.0, 0.0, 0.383, 0.924]);
+        assert_gains(pan(202.5, &speakers), &[0.0, 0.0, 0.924, 0.383]);
+    }
+
+    #[test]
+    fn speakers_can_be_in_any_order() {
+        let speakers = [135.0, -45.0, 225.0, 45.0];
+        assert_gains(pan(0.0, &speakers), &[0.0, 0.707, 0.0, 0.707]);
+        assert_gains(pan(180.0, &speakers), &[0.707, 0.0, 0.707, 0.0]);
+        assert_gains(pan(90.0, &speakers), &[0.707, 0.0, 0.0, 0.707]);
+    }
+
+    #[test]
+    fn one_speaker_gets_everything() {
+        assert_gains(pan(90.0, &[30.0]), &[1.0]);
+        assert!(pan(90.0, &[]).is_empty());
+    }
+
+    #[test]
+    fn speakers_can_share_an_azimuth() {
+        let speakers = [0.0, 0.0, 90.0];
+        assert_gains(pan(0.0, &speakers), &[1.0, 0.0, 0.0]);
+        assert_gains(pan(45.0, &speakers), &[0.0, 0.707, 0.707]);
+
+        // When every speaker shares an azimuth, the first one plays everything
+        assert_gains(pan(10.0, &[10.0, 10.0]), &[1.0, 0.0]);
+        assert_gains(pan(50.0, &[10.0, 10.0]), &[1.0, 0.0]);
+    }
+}
@@ -0,0 +1,81 @@
+/// The positions that tones are generated at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    Center,
+    RightFront,
+    RightMiddle,
+    RightRear,
+    RearCenter,
+    LeftRear,
+    LeftMiddle,
+    LeftFront,
+    LeftSurround,
+    RightSurround,
+}
+
+impl Position {
+    /// The positions that every matrix generates tones at, in the order that they are written
+    pub const STANDARD: [Position; 8] = [
+        Position::Center,
+        Position::RightFront,
+        Position::RightMiddle,
+        Position::RightRear,
+        Position::RearCenter,
+        Position::LeftRear,
+        Position::LeftMiddle,
+        Position::LeftFront,
+    ];
+
+    /// Every position, including the ones that only some matrixes generate tones at
+    pub const ALL: [Position; 10] = [
+        Position::Center,
+        Position::RightFront,
+        Position::RightMiddle,
+        Position::RightRear,
+        Position::RearCenter,
+        Position::LeftRear,
+        Position::LeftMiddle,
+        Position::LeftFront,
+        Position::LeftSurround,
+        Position::RightSurround,
+    ];
+
+    /// Looks up a position by its name, such as "right rear"
+    pub fn from_name(name: &str) -> Option<Position> {
+        Position::ALL
+            .into_iter()
+            .find(|position| position.name() == name)
+    }
+
+    /// A human-readable name, such as "right rear"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Position::Center => "center",
+            Position::RightFront => "right front",
+            Position::RightMiddle => "right middle",
+            Position::RightRear => "right rear",
+            Position::RearCenter => "rear center",
+            Position::LeftRear => "left rear",
+            Position::LeftMiddle => "left middle",
+            Position::LeftFront => "left front",
+            Position::LeftSurround => "left surround",
+            Position::RightSurround => "right surround",
+        }
+    }
+
+    /// The position's azimuth in degrees: 0 is center, positive is to the right, and 180 is rear
+    pub fn azimuth(&self) -> f32 {
+        match self {
+            Position::Center => 0.0,
+            Position::RightFront => 45.0,
+            Position::RightMiddle => 90.0,
+            Position::RightRear => 135.0,
+            Position::RearCenter => 180.0,
+            Position::LeftRear => -135.0,
+            Position::LeftMiddle => -90.0,
+            Position::LeftFront => -45.0,
+            Position::LeftSurround => -110.0,
+            Position::RightSurround => 110.0,
+        }
+    }
+}
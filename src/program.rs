@@ -0,0 +1,551 @@
+use std::{fs, io::Result, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    invalid,
+    matrix::{load_matrix, CustomMatrix, MatrixEncoder},
+    position::Position,
+    sequence::{Placement, Tone},
+    signal::{NoiseColor, Signal},
+};
+
+/// The level that a program's signals are written at when a segment doesn't set one, in dBFS
+pub const DEFAULT_LEVEL: f32 = -12.0;
+
+/// A test program: Signals and silences that are written one after another into a stereo wav
+/// file. Programs are read from JSON files, so new tests can be written without recompiling
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub segments: Vec<ProgramSegment>,
+}
+
+/// One part of a program
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgramSegment {
+    /// `duration` seconds of silence
+    Silence { duration: f32 },
+    /// A signal, encoded with a matrix at a placement, that lasts `duration` seconds
+    Signal {
+        signal: Signal,
+        /// The signal's peak amplitude, where 1 is full scale
+        amplitude: f32,
+        matrix: String,
+        tone: Tone,
+        duration: f32,
+        /// The cue's label at the start of the signal
+        label: String,
+    },
+}
+
+impl ProgramSegment {
+    /// How long the segment lasts, in seconds
+    pub fn duration(&self) -> f32 {
+        match self {
+            ProgramSegment::Silence { duration } => *duration,
+            ProgramSegment::Signal { duration, .. } => *duration,
+        }
+    }
+}
+
+impl Program {
+    /// Reads a program from a JSON file. Custom matrices' paths are relative to the program's file
+    pub fn read(path: &Path) -> Result<Program> {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        Program::parse_in(&fs::read_to_string(path)?, directory)
+    }
+
+    /// Parses a program from JSON, such as:
+    ///
+    /// ```json
+    /// {
+    ///     "matrix": "sq",
+    ///     "segments": [
+    ///         { "type": "silence", "duration": 0.5 },
+    ///         { "type": "tone", "frequency": 1000, "position": "left rear", "duration": 1 },
+    ///         { "type": "sweep", "start_frequency": 20, "end_frequency": 20000, "azimuth": 30,
+    ///           "level": -18, "duration": 5 },
+    ///         { "type": "noise", "color": "pink", "matrix": "qs", "position": "center",
+    ///           "duration": 2 }
+    ///     ]
+    /// }
+    /// ```
+    ///
+    /// Segments are "silence", "tone", "sweep", or "noise". A tone's "frequency" is a number or a
+    /// list of numbers, which is written as a multitone. Noise's "low_frequency",
+    /// "high_frequency", and "seed" default to 20, 20000, and 1. Every signal is placed at a
+    /// "position" (such as "right front") or an "azimuth" in degrees, and is encoded with its
+    /// "matrix", or the program's. A matrix is a built-in matrix's name, or a custom matrix's .json
+    /// file, relative to the current directory. "level" is the peak level in dBFS, and defaults to
+    /// -12. Durations are in seconds. Each signal has a cue at its start, labeled with its "label",
+    /// or its position and signal
+    pub fn parse(json: &str) -> Result<Program> {
+        Program::parse_in(json, Path::new(""))
+    }
+
+    // Parses a program whose custom matrices' paths are relative to `directory`
+    fn parse_in(json: &str, directory: &Path) -> Result<Program> {
+        let file: ProgramFile = serde_json::from_str(json)?;
+
+        if file.segments.is_empty() {
+            return Err(invalid("A program needs at least one segment".to_string()));
+        }
+
+        let segments = file
+            .segments
+            .into_iter()
+            .enumerate()
+            .map(|(index, segment)| {
+                segment
+                    .validate(file.matrix.as_deref(), directory)
+                    .map_err(|message| invalid(format!("Segment {}: {}", index + 1, message)))
+            })
+            .collect::<Result<Vec<ProgramSegment>>>()?;
+
+        Ok(Program { segments })
+    }
+
+    /// Checks that every frequency in the program can be written at the sample rate
+    pub fn validate_sample_rate(&self, sample_rate: u32) -> Result<()> {
+        let nyquist = sample_rate as f32 / 2.0;
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            let frequencies = match segment {
+                ProgramSegment::Silence { .. } => continue,
+                ProgramSegment::Signal { signal, .. } => match signal {
+                    Signal::Tone { frequencies } => frequencies.clone(),
+                    Signal::Sweep {
+                        start_frequency,
+                        end_frequency,
+                    } => vec![*start_frequency, *end_frequency],
+                    // The noise's band is limited to what the sample rate can hold
+                    Signal::Noise { low_frequency, .. } => vec![*low_frequency],
+                },
+            };
+
+            if let Some(frequency) = frequencies.iter().find(|frequency| **frequency >= nyquist) {
+                return Err(invalid(format!(
+                    "Segment {}: The frequency ({} Hz) must be below half the sample rate ({} Hz)",
+                    index + 1,
+                    frequency,
+                    nyquist
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgramFile {
+    // Used by segments that don't name a matrix
+    matrix: Option<String>,
+    segments: Vec<SegmentFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frequencies {
+    One(f32),
+    Many(Vec<f32>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SegmentFile {
+    #[serde(rename = "type")]
+    kind: String,
+    duration: f32,
+    frequency: Option<Frequencies>,
+    start_frequency: Option<f32>,
+    end_frequency: Option<f32>,
+    color: Option<String>,
+    low_frequency: Option<f32>,
+    high_frequency: Option<f32>,
+    seed: Option<u64>,
+    level: Option<f32>,
+    matrix: Option<String>,
+    position: Option<String>,
+    azimuth: Option<f32>,
+    label: Option<String>,
+}
+
+impl SegmentFile {
+    fn validate(
+        self,
+        default_matrix: Option<&str>,
+        directory: &Path,
+    ) -> std::result::Result<ProgramSegment, String> {
+        if !(self.duration.is_finite() && self.duration > 0.0) {
+            return Err("\"duration\" must be greater than 0".to_string());
+        }
+
+        let signal = match self.kind.as_str() {
+            "silence" => {
+                self.expect_only_duration()?;
+                return Ok(ProgramSegment::Silence {
+                    duration: self.duration,
+                });
+            }
+            "tone" => {
+                let frequencies = match &self.frequency {
+                    Some(Frequencies::One(frequency)) => vec![*frequency],
+                    Some(Frequencies::Many(frequencies)) => frequencies.clone(),
+                    None => return Err("A tone needs a \"frequency\"".to_string()),
+                };
+                if frequencies.is_empty() {
+                    return Err("\"frequency\" must list at least one frequency".to_string());
+                }
+
+                Signal::Tone { frequencies }
+            }
+            "sweep" => match (self.start_frequency, self.end_frequency) {
+                (Some(start_frequency), Some(end_frequency)) => {
+                    if start_frequency == end_frequency {
+                        return Err("A sweep's start and end frequencies must differ".to_string());
+                    }
+
+                    Signal::Sweep {
+                        start_frequency,
+                        end_frequency,
+                    }
+                }
+                _ => {
+                    return Err(
+                        "A sweep needs a \"start_frequency\" and an \"end_frequency\"".to_string(),
+                    )
+                }
+            },
+            "noise" => {
+                let color = match self.color.as_deref() {
+                    Some("white") => NoiseColor::White,
+                    Some("pink") => NoiseColor::Pink,
+                    Some(other) => {
+                        return Err(format!(
+                            "Unknown noise color \"{}\", expected white or pink",
+                            other
+                        ))
+                    }
+                    None => return Err("Noise needs a \"color\"".to_string()),
+                };
+                let low_frequency = self.low_frequency.unwrap_or(20.0);
+                let high_frequency = self.high_frequency.unwrap_or(20000.0);
+                if !(low_frequency >= 0.0 && low_frequency < high_frequency) {
+                    return Err(
+                        "\"low_frequency\" must be at least 0 and below \"high_frequency\""
+                            .to_string(),
+                    );
+                }
+
+                Signal::Noise {
+                    color,
+                    low_frequency,
+                    high_frequency,
+                    seed: self.seed.unwrap_or(1),
+                }
+            }
+            other => {
+                return Err(format!(
+                    "Unknown type \"{}\", expected silence, tone, sweep, or noise",
+                    other
+                ))
+            }
+        };
+
+        self.expect_fields_for(&signal)?;
+
+        let frequencies = match &signal {
+            Signal::Tone { frequencies } => frequencies.clone(),
+            Signal::Sweep {
+                start_frequency,
+                end_frequency,
+            } => vec![*start_frequency, *end_frequency],
+            Signal::Noise { .. } => Vec::new(),
+        };
+        if frequencies
+            .iter()
+            .any(|frequency| !frequency.is_finite() || *frequency <= 0.0)
+        {
+            return Err("Frequencies must be greater than 0".to_string());
+        }
+
+        let level = self.level.unwrap_or(DEFAULT_LEVEL);
+        if !(level.is_finite() && level <= 0.0) {
+            return Err("\"level\" must be at most 0 dBFS".to_string());
+        }
+
+        let matrix_name = match self.matrix.as_deref().or(default_matrix) {
+            Some(matrix_name) => matrix_name,
+            None => return Err("No \"matrix\" for the segment or the program".to_string()),
+        };
+        let encoder: Box<dyn MatrixEncoder> = if matrix_name.ends_with(".json") {
+            let path = directory.join(matrix_name);
+            Box::new(
+                CustomMatrix::read(&path)
+                    .map_err(|err| format!("Can not read {}: {}", path.display(), err))?,
+            )
+        } else {
+            load_matrix(matrix_name).map_err(|err| err.to_string())?
+        };
+
+        let placement = match (&self.position, self.azimuth) {
+            (Some(name), None) => Placement::Position(
+                Position::from_name(name)
+                    .ok_or_else(|| format!("Unknown position \"{}\"", name))?,
+            ),
+            (None, Some(azimuth)) if azimuth.is_finite() => Placement::Azimuth(azimuth),
+            (None, Some(_)) => return Err("\"azimuth\" must be a finite number".to_string()),
+            _ => return Err("A signal needs either a \"position\" or an \"azimuth\"".to_string()),
+        };
+        let (left_total, right_total) = placement.encode(encoder.as_ref());
+        let label = self
+            .label
+            .unwrap_or_else(|| format!("{}, {}", placement.name(), signal.describe()));
+
+        Ok(ProgramSegment::Signal {
+            signal,
+            amplitude: 10f32.powf(level / 20.0),
+            matrix: encoder.name().to_string(),
+            tone: Tone {
+                placement,
+                left_total,
+                right_total,
+            },
+            duration: self.duration,
+            label,
+        })
+    }
+
+    fn expect_only_duration(&self) -> std::result::Result<(), String> {
+        let has_others = self.frequency.is_some()
+            || self.start_frequency.is_some()
+            || self.end_frequency.is_some()
+            || self.color.is_some()
+            || self.low_frequency.is_some()
+            || self.high_frequency.is_some()
+            || self.seed.is_some()
+            || self.level.is_some()
+            || self.matrix.is_some()
+            || self.position.is_some()
+            || self.azimuth.is_some()
+            || self.label.is_some();
+
+        if has_others {
+            Err("Silence only has a \"duration\"".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    // Catches fields that belong to a different type of signal, which are probably mistakes
+    fn expect_fields_for(&self, signal: &Signal) -> std::result::Result<(), String> {
+        let (is_tone, is_sweep, is_noise) = match signal {
+            Signal::Tone { .. } => (true, false, false),
+            Signal::Sweep { .. } => (false, true, false),
+            Signal::Noise { .. } => (false, false, true),
+        };
+
+        let fields = [
+            ("frequency", self.frequency.is_some(), is_tone),
+            ("start_frequency", self.start_frequency.is_some(), is_sweep),
+            ("end_frequency", self.end_frequency.is_some(), is_sweep),
+            ("color", self.color.is_some(), is_noise),
+            ("low_frequency", self.low_frequency.is_some(), is_noise),
+            ("high_frequency", self.high_frequency.is_some(), is_noise),
+            ("seed", self.seed.is_some(), is_noise),
+        ];
+        for (field, present, allowed) in fields {
+            if present && !allowed {
+                return Err(format!(
+                    "\"{}\" can not be used in a {} segment",
+                    field, self.kind
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(json: &str) -> String {
+        Program::parse(json).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parses_segments() {
+        let program = Program::parse(
+            r#"{
+                "matrix": "sq",
+                "segments": [
+                    { "type": "silence", "duration": 0.5 },
+                    { "type": "tone", "frequency": [500, 1000], "position": "left rear",
+                      "duration": 1 },
+                    { "type": "sweep", "start_frequency": 20, "end_frequency": 20000,
+                      "azimuth": 30, "level": -6, "matrix": "qs", "duration": 2,
+                      "label": "sweep" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(program.segments.len(), 3);
+        assert_eq!(
+            program.segments[0],
+            ProgramSegment::Silence { duration: 0.5 }
+        );
+
+        match &program.segments[1] {
+            ProgramSegment::Signal {
+                signal,
+                amplitude,
+                matrix,
+                tone,
+                label,
+                ..
+            } => {
+                assert_eq!(
+                    *signal,
+                    Signal::Tone {
+                        frequencies: vec![500.0, 1000.0]
+                    }
+                );
+                assert!((amplitude - 10f32.powf(DEFAULT_LEVEL / 20.0)).abs() < 1e-6);
+                assert_eq!(matrix, "sq");
+                assert_eq!(tone.placement, Placement::Position(Position::LeftRear));
+                assert_eq!(label, "left rear, 500 + 1000 Hz tone");
+            }
+            segment => panic!("Expected a signal, got {:?}", segment),
+        }
+
+        match &program.segments[2] {
+            ProgramSegment::Signal {
+                amplitude,
+                matrix,
+                tone,
+                duration,
+                label,
+                ..
+            } => {
+                assert!((amplitude - 0.501).abs() < 0.001);
+                assert_eq!(matrix, "qs");
+                assert_eq!(tone.placement, Placement::Azimuth(30.0));
+                assert_eq!(*duration, 2.0);
+                assert_eq!(label, "sweep");
+            }
+            segment => panic!("Expected a signal, got {:?}", segment),
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_fields() {
+        assert_eq!(
+            parse_error(
+                r#"{ "matrix": "sq", "segments": [
+                    { "type": "sweep", "start_frequency": 20, "end_frequency": 200,
+                      "frequency": 100, "position": "center", "duration": 1 }
+                ] }"#
+            ),
+            "Segment 1: \"frequency\" can not be used in a sweep segment"
+        );
+        assert_eq!(
+            parse_error(
+                r#"{ "matrix": "sq", "segments": [
+                    { "type": "silence", "duration": 1 },
+                    { "type": "tone", "frequency": 100, "position": "center", "azimuth": 0,
+                      "duration": 1 }
+                ] }"#
+            ),
+            "Segment 2: A signal needs either a \"position\" or an \"azimuth\""
+        );
+        assert_eq!(
+            parse_error(r#"{ "segments": [ { "type": "silence", "duration": 1, "level": -6 } ] }"#),
+            "Segment 1: Silence only has a \"duration\""
+        );
+    }
+
+    #[test]
+    fn rejects_bad_names() {
+        assert_eq!(
+            parse_error(
+                r#"{ "matrix": "sq", "segments": [
+                    { "type": "tone", "frequency": 100, "position": "upstairs", "duration": 1 }
+                ] }"#
+            ),
+            "Segment 1: Unknown position \"upstairs\""
+        );
+        assert_eq!(
+            parse_error(
+                r#"{ "matrix": "sq", "segments": [
+                    { "type": "chirp", "duration": 1 }
+                ] }"#
+            ),
+            "Segment 1: Unknown type \"chirp\", expected silence, tone, sweep, or noise"
+        );
+        assert!(parse_error(
+            r#"{ "matrix": "nonexistent", "segments": [
+                { "type": "tone", "frequency": 100, "position": "center", "duration": 1 }
+            ] }"#
+        )
+        .starts_with("Segment 1: "));
+    }
+
+    #[test]
+    fn rejects_a_missing_matrix() {
+        assert_eq!(
+            parse_error(
+                r#"{ "segments": [
+                    { "type": "tone", "frequency": 100, "position": "center", "duration": 1 }
+                ] }"#
+            ),
+            "Segment 1: No \"matrix\" for the segment or the program"
+        );
+    }
+
+    #[test]
+    fn rejects_frequencies_above_nyquist() {
+        let program = Program::parse(
+            r#"{ "matrix": "sq", "segments": [
+                { "type": "tone", "frequency": 30000, "position": "center", "duration": 1 }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert!(program.validate_sample_rate(96000).is_ok());
+        assert!(program.validate_sample_rate(48000).is_err());
+    }
+
+    #[test]
+    fn reads_custom_matrices_next_to_the_program() {
+        let directory = std::env::temp_dir().join("soft_matrix_test_tones_program");
+        fs::create_dir_all(&directory).unwrap();
+        fs::copy("matrices/homebrew.json", directory.join("homebrew.json")).unwrap();
+        let program_path = directory.join("program.json");
+        fs::write(
+            &program_path,
+            r#"{ "matrix": "homebrew.json", "segments": [
+                { "type": "tone", "frequency": 100, "position": "center", "duration": 1 },
+                { "type": "tone", "frequency": 100, "position": "center", "matrix": "missing.json",
+                  "duration": 1 }
+            ] }"#,
+        )
+        .unwrap();
+
+        let err = Program::read(&program_path).unwrap_err().to_string();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(
+            err.starts_with(&format!(
+                "Segment 2: Can not read {}: ",
+                directory.join("missing.json").display()
+            )),
+            "{}",
+            err
+        );
+    }
+}
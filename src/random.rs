@@ -0,0 +1,40 @@
+/// A small, seeded pseudo-random number generator (SplitMix64). Test signals must be reproducible,
+/// so the same seed always produces the same numbers
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly-distributed number in 0..1
+    pub fn next_f64(&mut self) -> f64 {
+        // The top 53 bits fill an f64's mantissa
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A triangularly-distributed number in -1..1, as used for TPDF dither
+    pub fn next_triangular(&mut self) -> f64 {
+        self.next_f64() - self.next_f64()
+    }
+
+    /// A normally-distributed number with a mean of 0 and a standard deviation of 1
+    pub fn next_gaussian(&mut self) -> f64 {
+        // Box-Muller. 1 - next_f64() is never 0, so the log is always finite
+        let radius = (-2.0 * (1.0 - self.next_f64()).ln()).sqrt();
+        let angle = 2.0 * std::f64::consts::PI * self.next_f64();
+        radius * angle.cos()
+    }
+}
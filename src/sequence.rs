@@ -0,0 +1,119 @@
+use rustfft::num_complex::Complex;
+
+use crate::{matrix::MatrixEncoder, panning::normalize_azimuth, position::Position};
+
+/// Where a tone is: Either one of the named positions, or an arbitrary azimuth in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    Position(Position),
+    Azimuth(f32),
+}
+
+impl Placement {
+    /// A human-readable name, such as "right rear" or "azimuth 15°"
+    pub fn name(&self) -> String {
+        match self {
+            Placement::Position(position) => position.name().to_string(),
+            Placement::Azimuth(azimuth) => format!("azimuth {}°", azimuth),
+        }
+    }
+
+    /// The azimuth in degrees: 0 is center, positive is to the right, and 180 is rear
+    pub fn azimuth(&self) -> f32 {
+        match self {
+            Placement::Position(position) => position.azimuth(),
+            Placement::Azimuth(azimuth) => *azimuth,
+        }
+    }
+
+    /// Encodes the placement with the given matrix
+    pub fn encode(&self, encoder: &dyn MatrixEncoder) -> (Complex<f32>, Complex<f32>) {
+        match self {
+            Placement::Position(position) => encoder.encode_position(*position),
+            Placement::Azimuth(azimuth) => encoder.encode_azimuth(*azimuth),
+        }
+    }
+}
+
+/// A single tone: Where it is, and the gains applied to the left total and right total channels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tone {
+    pub placement: Placement,
+    pub left_total: Complex<f32>,
+    pub right_total: Complex<f32>,
+}
+
+/// Describes the tones written into a file, in order. Each tone is followed by silence
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToneSequence {
+    pub tones: Vec<Tone>,
+}
+
+impl ToneSequence {
+    /// Creates a sequence with a tone at each of the matrix's positions
+    pub fn for_matrix(encoder: &dyn MatrixEncoder) -> ToneSequence {
+        let placements: Vec<Placement> = encoder
+            .positions()
+            .into_iter()
+            .map(Placement::Position)
+            .collect();
+
+        ToneSequence::for_placements(encoder, &placements)
+    }
+
+    /// Creates a sequence with a tone at each azimuth, in degrees
+    pub fn for_azimuths(encoder: &dyn MatrixEncoder, azimuths: &[f32]) -> ToneSequence {
+        let placements: Vec<Placement> = azimuths
+            .iter()
+            .map(|azimuth| Placement::Azimuth(*azimuth))
+            .collect();
+
+        ToneSequence::for_placements(encoder, &placements)
+    }
+
+    /// Creates a sequence with a tone at each placement
+    pub fn for_placements(encoder: &dyn MatrixEncoder, placements: &[Placement]) -> ToneSequence {
+        let tones = placements
+            .iter()
+            .map(|placement| {
+                let (left_total, right_total) = placement.encode(encoder);
+                Tone {
+                    placement: *placement,
+                    left_total,
+                    right_total,
+                }
+            })
+            .collect();
+
+        ToneSequence { tones }
+    }
+}
+
+/// Azimuths every `step` degrees around the circle, starting at center and moving to the right
+pub fn azimuths_around_circle(step: f32) -> Vec<f32> {
+    let mut azimuths = Vec::new();
+    let mut step_ctr = 0;
+
+    loop {
+        let azimuth = step * step_ctr as f32;
+        if azimuth >= 360.0 {
+            break;
+        }
+
+        azimuths.push(normalize_signed(azimuth));
+        step_ctr += 1;
+    }
+
+    azimuths
+}
+
+// Keeps azimuths in -180..=180, so that left is negative
+fn normalize_signed(azimuth: f32) -> f32 {
+    let azimuth = normalize_azimuth(azimuth);
+
+    if azimuth > 180.0 {
+        azimuth - 360.0
+    } else {
+        azimuth
+    }
+}
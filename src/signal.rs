@@ -0,0 +1,306 @@
+use std::f64::consts::PI;
+
+use rustfft::{num_complex::Complex, FftPlanner};
+use serde::Serialize;
+
+use crate::random::Random;
+
+/// The spectrum of a noise signal
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseColor {
+    /// Equal energy per Hz
+    White,
+    /// Equal energy per octave: -3 dB per octave
+    Pink,
+}
+
+/// A test signal that is written at a position. Signals are rendered as complex (analytic)
+/// samples: The real part is the signal, and the imaginary part is the signal shifted by 90°, so
+/// multiplying by a matrix's gain scales and phase-shifts the signal
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Signal {
+    /// Sine waves at one or more frequencies, in Hz. More than one frequency is a multitone,
+    /// which is scaled so that it never peaks above a single tone
+    Tone { frequencies: Vec<f32> },
+    /// An exponential (Farina) sine sweep from `start_frequency` to `end_frequency`, in Hz
+    Sweep {
+        start_frequency: f32,
+        end_frequency: f32,
+    },
+    /// Noise between `low_frequency` and `high_frequency`, in Hz. The same seed always renders the
+    /// same noise
+    Noise {
+        color: NoiseColor,
+        low_frequency: f32,
+        high_frequency: f32,
+        seed: u64,
+    },
+}
+
+impl Signal {
+    /// A human-readable description, such as "1000 Hz tone"
+    pub fn describe(&self) -> String {
+        match self {
+            Signal::Tone { frequencies } => {
+                let frequencies: Vec<String> = frequencies
+                    .iter()
+                    .map(|frequency| format!("{}", frequency))
+                    .collect();
+                format!("{} Hz tone", frequencies.join(" + "))
+            }
+            Signal::Sweep {
+                start_frequency,
+                end_frequency,
+            } => format!(
+                "{} Hz to {} Hz logarithmic sweep",
+                start_frequency, end_frequency
+            ),
+            Signal::Noise {
+                color,
+                low_frequency,
+                high_frequency,
+                seed,
+            } => format!(
+                "{} noise from {} Hz to {} Hz (seed {})",
+                match color {
+                    NoiseColor::White => "white",
+                    NoiseColor::Pink => "pink",
+                },
+                low_frequency,
+                high_frequency,
+                seed
+            ),
+        }
+    }
+
+    /// Renders `length` samples with a peak amplitude of 1. Noise's peak is normalized to 1, so it is
+    /// quieter than a tone
+    pub fn render(&self, sample_rate: u32, length: usize) -> Vec<Complex<f32>> {
+        match self {
+            Signal::Tone { frequencies } => render_tone(frequencies, sample_rate, length),
+            Signal::Sweep {
+                start_frequency,
+                end_frequency,
+            } => render_sweep(*start_frequency, *end_frequency, sample_rate, length),
+            Signal::Noise {
+                color,
+                low_frequency,
+                high_frequency,
+                seed,
+            } => render_noise(
+                *color,
+                *low_frequency,
+                *high_frequency,
+                *seed,
+                sample_rate,
+                length,
+            ),
+        }
+    }
+}
+
+fn render_tone(frequencies: &[f32], sample_rate: u32, length: usize) -> Vec<Complex<f32>> {
+    let amplitude = 1.0 / frequencies.len() as f32;
+
+    (0..length)
+        .map(|sample| {
+            frequencies
+                .iter()
+                .map(|frequency| {
+                    // Calculated in f64, and wrapped to a single cycle, so that long tones don't
+                    // lose precision
+                    let cycles = (*frequency as f64 * sample as f64 / sample_rate as f64).fract();
+                    Complex::from_polar(amplitude, (2.0 * PI * cycles) as f32)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+// See Farina, "Simultaneous measurement of impulse response and distortion with a swept-sine
+// technique": The phase is 2π f1 T / ln(f2 / f1) * (e^(t / T * ln(f2 / f1)) - 1)
+fn render_sweep(
+    start_frequency: f32,
+    end_frequency: f32,
+    sample_rate: u32,
+    length: usize,
+) -> Vec<Complex<f32>> {
+    let start_frequency = start_frequency as f64;
+    let duration = length as f64 / sample_rate as f64;
+    let rate = (end_frequency as f64 / start_frequency).ln();
+
+    (0..length)
+        .map(|sample| {
+            let time = sample as f64 / sample_rate as f64;
+            let cycles = start_frequency * duration / rate * ((time / duration * rate).exp() - 1.0);
+            Complex::from_polar(1.0, (2.0 * PI * cycles.fract()) as f32)
+        })
+        .collect()
+}
+
+// Noise is generated in the frequency domain. Only positive frequencies are filled in, so the
+// inverse FFT is already analytic: The imaginary part is the real part shifted by 90° at every
+// frequency, which a matrix's phase shifts rely on
+fn render_noise(
+    color: NoiseColor,
+    low_frequency: f32,
+    high_frequency: f32,
+    seed: u64,
+    sample_rate: u32,
+    length: usize,
+) -> Vec<Complex<f32>> {
+    if length == 0 {
+        return Vec::new();
+    }
+
+    let mut random = Random::new(seed);
+    let mut spectrum = vec![Complex::new(0.0f32, 0.0f32); length];
+
+    for (bin, value) in spectrum.iter_mut().enumerate().take(length / 2 + 1).skip(1) {
+        let frequency = bin as f32 * sample_rate as f32 / length as f32;
+
+        // Random numbers are always drawn, so that the band doesn't change the noise within it
+        let re = random.next_gaussian() as f32;
+        let im = random.next_gaussian() as f32;
+
+        if frequency < low_frequency || frequency > high_frequency {
+            continue;
+        }
+
+        let magnitude = match color {
+            NoiseColor::White => 1.0,
+            NoiseColor::Pink => 1.0 / frequency.sqrt(),
+        };
+
+        *value = Complex::new(re, im) * magnitude;
+    }
+
+    let mut planner = FftPlanner::new();
+    let fft_inverse = planner.plan_fft_inverse(length);
+    fft_inverse.process(&mut spectrum);
+
+    let peak = spectrum
+        .iter()
+        .map(|sample| sample.norm())
+        .fold(0.0f32, f32::max);
+
+    if peak > 0.0 {
+        for sample in spectrum.iter_mut() {
+            *sample /= peak;
+        }
+    }
+
+    spectrum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(seed: u64) -> Signal {
+        Signal::Noise {
+            color: NoiseColor::Pink,
+            low_frequency: 1000.0,
+            high_frequency: 5000.0,
+            seed,
+        }
+    }
+
+    #[test]
+    fn noise_is_reproducible() {
+        let noise_1 = noise(1).render(48000, 4800);
+
+        assert_eq!(noise_1, noise(1).render(48000, 4800));
+        assert_ne!(noise_1, noise(2).render(48000, 4800));
+    }
+
+    #[test]
+    fn noise_is_band_limited_and_normalized() {
+        let mut spectrum = noise(1).render(48000, 4800);
+
+        let peak = spectrum
+            .iter()
+            .map(|sample| sample.norm())
+            .fold(0.0f32, f32::max);
+        assert!((peak - 1.0).abs() < 1e-6);
+
+        FftPlanner::new()
+            .plan_fft_forward(spectrum.len())
+            .process(&mut spectrum);
+
+        // Each bin is 10 Hz, and negative frequencies are past the middle
+        let total: f32 = spectrum.iter().map(|bin| bin.norm_sqr()).sum();
+        for (bin, value) in spectrum.iter().enumerate() {
+            let in_band = (100..=500).contains(&bin);
+            if !in_band {
+                assert!(value.norm_sqr() < total * 1e-9, "Bin {} has energy", bin);
+            }
+        }
+    }
+
+    #[test]
+    fn sweep_rises_exponentially() {
+        let sample_rate = 48000;
+        let sweep = Signal::Sweep {
+            start_frequency: 100.0,
+            end_frequency: 10000.0,
+        }
+        .render(sample_rate, 48000);
+
+        // The phase difference between two samples is the instantaneous frequency
+        let frequency_at = |sample: usize| {
+            (sweep[sample + 1] * sweep[sample].conj()).arg() as f64 * sample_rate as f64
+                / (2.0 * PI)
+        };
+
+        for (sample, expected) in [
+            (0, 100.0),
+            (12000, 316.2),
+            (24000, 1000.0),
+            (36000, 3162.3),
+            (47998, 10000.0),
+        ] {
+            let frequency = frequency_at(sample);
+            assert!(
+                (frequency / expected - 1.0).abs() < 0.01,
+                "{} Hz at sample {}, expected {} Hz",
+                frequency,
+                sample,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn tone_is_continuous_across_windows() {
+        // 1000 Hz doesn't fit a whole number of cycles into the 50-sample windows that tones used
+        // to repeat
+        let tone = Signal::Tone {
+            frequencies: vec![1000.0],
+        }
+        .render(44100, 1000);
+
+        for (sample_ctr, sample) in tone.iter().enumerate() {
+            let phase = 2.0 * PI * 1000.0 * sample_ctr as f64 / 44100.0;
+            assert!((sample.re as f64 - phase.cos()).abs() < 1e-5);
+            assert!((sample.im as f64 - phase.sin()).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn multitone_never_peaks_above_1() {
+        let multitone = Signal::Tone {
+            frequencies: vec![100.0, 200.0, 300.0, 1000.0],
+        }
+        .render(44100, 44100);
+
+        let peak = multitone
+            .iter()
+            .map(|sample| sample.norm())
+            .fold(0.0f32, f32::max);
+        assert!(peak <= 1.0 + 1e-6);
+        assert!(peak > 0.99);
+    }
+}
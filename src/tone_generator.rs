@@ -0,0 +1,548 @@
+use std::{io::Result, path::Path};
+
+use wave_stream::{samples_by_channel::SamplesByChannel, wave_header::Channels};
+
+use crate::{
+    fade::Fade,
+    manifest::{Manifest, ManifestSegment},
+    matrix::{apply_gains, Gains, MatrixEncoder},
+    program::{Program, ProgramSegment},
+    sequence::{Placement, Tone, ToneSequence},
+    signal::Signal,
+    writer::{OutputFormat, WavWriter},
+};
+
+// About -11 dBFS, which leaves headroom for matrixes whose gains are greater than 1. This is the level
+// that tones were written at when they were inverse FFTs: A tone in bin 1 of a 50-sample window, and
+// its mirror image, scaled by rustfft's 1/sqrt(len), is 2/sqrt(50)
+const AMPLITUDE: f32 = 0.282_842_7;
+
+/// How a tone with more than one frequency is written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrequencyMode {
+    /// Each frequency is written as its own tone, followed by silence
+    Sequential,
+    /// All frequencies are written at the same time, as a single multitone
+    Multitone,
+}
+
+impl FrequencyMode {
+    /// The tones that are written at each position for the frequencies
+    pub fn signals(&self, frequencies: Vec<f32>) -> Vec<Signal> {
+        match self {
+            FrequencyMode::Sequential => frequencies
+                .into_iter()
+                .map(|frequency| Signal::Tone {
+                    frequencies: vec![frequency],
+                })
+                .collect(),
+            FrequencyMode::Multitone => vec![Signal::Tone { frequencies }],
+        }
+    }
+}
+
+/// Where a signal is written in a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    /// The first sample of the signal
+    pub start: usize,
+    /// How many samples the signal lasts
+    pub length: usize,
+    pub tone: Tone,
+    pub signal: Signal,
+}
+
+/// Writes tone sequences into stereo (left total, right total) wav files
+pub struct ToneGenerator {
+    sample_rate: u32,
+    output_format: OutputFormat,
+    signals: Vec<Signal>,
+    fade: Fade,
+    // Durations are in samples
+    tone_length: usize,
+    silence_length: usize,
+
+    sample_ctr: usize,
+}
+
+impl ToneGenerator {
+    /// Creates a generator for tones at `frequency` Hz. Each tone lasts `tone_length` samples and is
+    /// followed by `silence_length` samples of silence
+    pub fn new(
+        sample_rate: u32,
+        frequency: f32,
+        tone_length: usize,
+        silence_length: usize,
+    ) -> ToneGenerator {
+        ToneGenerator {
+            sample_rate,
+            output_format: OutputFormat::default(),
+            signals: vec![Signal::Tone {
+                frequencies: vec![frequency],
+            }],
+            fade: Fade::default(),
+            tone_length,
+            silence_length,
+            sample_ctr: 0,
+        }
+    }
+
+    /// Creates a generator for tones at `frequency` Hz, where each tone lasts `tone_duration` seconds
+    /// and is followed by `silence_duration` seconds of silence. Frequencies and durations are the
+    /// same at every sample rate
+    pub fn with_durations(
+        sample_rate: u32,
+        frequency: f32,
+        tone_duration: f32,
+        silence_duration: f32,
+    ) -> ToneGenerator {
+        ToneGenerator::new(
+            sample_rate,
+            frequency,
+            seconds_to_samples(sample_rate, tone_duration),
+            seconds_to_samples(sample_rate, silence_duration),
+        )
+    }
+
+    /// Writes each tone at multiple frequencies, either one after another or all at once. Phase-shift
+    /// networks are frequency dependent, so this exposes steering errors that only happen at some
+    /// frequencies
+    pub fn set_frequencies(&mut self, frequencies: Vec<f32>, frequency_mode: FrequencyMode) {
+        self.signals = frequency_mode.signals(frequencies);
+    }
+
+    /// Writes each of the signals, in order, at every position. Each signal is followed by silence
+    pub fn set_signals(&mut self, signals: Vec<Signal>) {
+        self.signals = signals;
+    }
+
+    /// Fades each tone in and out, so that tones don't click when they start and stop. Defaults to
+    /// `Fade::default()`
+    pub fn set_fade(&mut self, fade: Fade) {
+        self.fade = fade;
+    }
+
+    /// Writes 16-bit, 24-bit, or 32-bit float wav files, optionally with dither. Defaults to 32-bit
+    /// float
+    pub fn set_output_format(&mut self, output_format: OutputFormat) {
+        self.output_format = output_format;
+    }
+
+    /// Writes a wav file with a tone at each of the matrix's positions
+    pub fn write_all_tones(&mut self, path: &Path, encoder: &dyn MatrixEncoder) -> Result<()> {
+        self.write_sequence(path, &ToneSequence::for_matrix(encoder))
+    }
+
+    /// Writes a wav file with a tone at each azimuth, in degrees, encoded with the given matrix
+    pub fn write_azimuth_tones(
+        &mut self,
+        path: &Path,
+        encoder: &dyn MatrixEncoder,
+        azimuths: &[f32],
+    ) -> Result<()> {
+        self.write_sequence(path, &ToneSequence::for_azimuths(encoder, azimuths))
+    }
+
+    /// Writes a wav file with a tone that pans once around the circle, encoded with the given
+    /// matrix. The tone starts at center, moves to the right, and lasts `duration` seconds. The
+    /// matrix's gains are recalculated for every sample, so the tone moves smoothly. With more than
+    /// one signal, the tone pans around the circle once per signal
+    pub fn write_panning_sweep(
+        &mut self,
+        path: &Path,
+        encoder: &dyn MatrixEncoder,
+        duration: f32,
+    ) -> Result<()> {
+        self.sample_ctr = 0;
+
+        let mut writer = self.create_writer(path)?;
+
+        self.write_silence(&mut writer, self.silence_length)?;
+
+        let samples_in_sweep = seconds_to_samples(self.sample_rate, duration);
+        let envelope = self.fade.envelope(self.sample_rate, samples_in_sweep);
+        for signal in self.signals.clone() {
+            let rendered = signal.render(self.sample_rate, samples_in_sweep);
+            writer.add_cue(
+                self.sample_ctr,
+                self.cue_label("panning sweep".to_string(), &signal),
+            );
+            for (sweep_ctr, (sample, gain)) in rendered.iter().zip(envelope.iter()).enumerate() {
+                let azimuth = 360.0 * sweep_ctr as f32 / samples_in_sweep as f32;
+                let (left_total, right_total) =
+                    apply_gains(encoder.encode_azimuth(azimuth), sample * gain * AMPLITUDE);
+
+                let samples_by_channel = SamplesByChannel::new()
+                    .front_left(left_total)
+                    .front_right(right_total);
+
+                writer.write_samples(self.sample_ctr, samples_by_channel)?;
+
+                self.sample_ctr += 1;
+            }
+
+            self.write_silence(&mut writer, self.silence_length)?;
+        }
+
+        writer.finish()
+    }
+
+    /// Describes each sweep that `write_panning_sweep` writes, encoded with the matrix. The gains
+    /// change with every sample, so they aren't listed
+    pub fn panning_sweep_manifest(&self, encoder: &dyn MatrixEncoder, duration: f32) -> Manifest {
+        let samples_in_sweep = seconds_to_samples(self.sample_rate, duration);
+
+        let mut segments = Vec::new();
+        let mut start = self.silence_length;
+        for signal in self.signals.iter() {
+            segments.push(ManifestSegment {
+                label: self.cue_label("panning sweep".to_string(), signal),
+                start,
+                length: samples_in_sweep,
+                position: None,
+                azimuth: None,
+                matrix: encoder.name().to_string(),
+                signal: signal.clone(),
+                amplitude: AMPLITUDE,
+                left_total: None,
+                right_total: None,
+            });
+
+            start += samples_in_sweep + self.silence_length;
+        }
+
+        Manifest {
+            sample_rate: self.sample_rate,
+            segments,
+        }
+    }
+
+    /// Where `write_sequence` writes each signal for the sequence, in order
+    pub fn segments(&self, sequence: &ToneSequence) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut start = self.silence_length;
+        for tone in sequence.tones.iter() {
+            for signal in self.signals.iter() {
+                segments.push(Segment {
+                    start,
+                    length: self.tone_length,
+                    tone: *tone,
+                    signal: signal.clone(),
+                });
+
+                start += self.tone_length + self.silence_length;
+            }
+        }
+
+        segments
+    }
+
+    /// Describes each signal that `write_sequence` writes for the sequence, encoded with the matrix
+    pub fn manifest(&self, encoder: &dyn MatrixEncoder, sequence: &ToneSequence) -> Manifest {
+        let segments = self
+            .segments(sequence)
+            .into_iter()
+            .map(|segment| {
+                manifest_segment(
+                    self.cue_label(segment.tone.placement.name(), &segment.signal),
+                    segment.start,
+                    segment.length,
+                    encoder.name().to_string(),
+                    segment.signal,
+                    AMPLITUDE,
+                    segment.tone,
+                )
+            })
+            .collect();
+
+        Manifest {
+            sample_rate: self.sample_rate,
+            segments,
+        }
+    }
+
+    /// Describes each signal that `write_program` writes for the program
+    pub fn program_manifest(&self, program: &Program) -> Manifest {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        for segment in program.segments.iter() {
+            let length = seconds_to_samples(self.sample_rate, segment.duration());
+            if let ProgramSegment::Signal {
+                signal,
+                amplitude,
+                matrix,
+                tone,
+                label,
+                ..
+            } = segment
+            {
+                segments.push(manifest_segment(
+                    label.clone(),
+                    start,
+                    length,
+                    matrix.clone(),
+                    signal.clone(),
+                    *amplitude,
+                    *tone,
+                ));
+            }
+
+            start += length;
+        }
+
+        Manifest {
+            sample_rate: self.sample_rate,
+            segments,
+        }
+    }
+
+    /// Writes a wav file that starts with silence, followed by each tone in the sequence. Each tone
+    /// has a cue, labeled with its position, so DAWs show where it starts
+    pub fn write_sequence(&mut self, path: &Path, sequence: &ToneSequence) -> Result<()> {
+        self.sample_ctr = 0;
+
+        let mut writer = self.create_writer(path)?;
+
+        self.write_silence(&mut writer, self.silence_length)?;
+
+        for tone in sequence.tones.iter() {
+            for signal in self.signals.clone() {
+                writer.add_cue(
+                    self.sample_ctr,
+                    self.cue_label(tone.placement.name(), &signal),
+                );
+                self.write_signal(
+                    &mut writer,
+                    &signal,
+                    (tone.left_total, tone.right_total),
+                    AMPLITUDE,
+                    self.tone_length,
+                )?;
+                self.write_silence(&mut writer, self.silence_length)?;
+            }
+        }
+
+        writer.finish()
+    }
+
+    /// Writes a wav file with each of the program's segments, one after another. The program's
+    /// durations and levels are used instead of the generator's signals and durations
+    pub fn write_program(&mut self, path: &Path, program: &Program) -> Result<()> {
+        program.validate_sample_rate(self.sample_rate)?;
+
+        self.sample_ctr = 0;
+
+        let mut writer = self.create_writer(path)?;
+
+        for segment in program.segments.iter() {
+            let length = seconds_to_samples(self.sample_rate, segment.duration());
+            match segment {
+                ProgramSegment::Silence { .. } => self.write_silence(&mut writer, length)?,
+                ProgramSegment::Signal {
+                    signal,
+                    amplitude,
+                    tone,
+                    label,
+                    ..
+                } => {
+                    writer.add_cue(self.sample_ctr, label.clone());
+                    self.write_signal(
+                        &mut writer,
+                        signal,
+                        (tone.left_total, tone.right_total),
+                        *amplitude,
+                        length,
+                    )?
+                }
+            }
+        }
+
+        writer.finish()
+    }
+
+    // Cues are named after where the signal is, and the signal when there's more than one
+    fn cue_label(&self, name: String, signal: &Signal) -> String {
+        if self.signals.len() > 1 {
+            format!("{}, {}", name, signal.describe())
+        } else {
+            name
+        }
+    }
+
+    fn create_writer(&self, path: &Path) -> Result<WavWriter> {
+        WavWriter::create(
+            path,
+            Channels::new().front_left().front_right(),
+            self.sample_rate,
+            self.output_format,
+        )
+    }
+
+    // The signal is generated one sample at a time, instead of repeating a window, so that
+    // frequencies that don't fit evenly into a window don't have discontinuities at the window
+    // boundaries. The signal is faded in and out, so the only energy at its start and end is the
+    // signal itself
+    fn write_signal(
+        &mut self,
+        writer: &mut WavWriter,
+        signal: &Signal,
+        gains: Gains,
+        amplitude: f32,
+        length: usize,
+    ) -> Result<()> {
+        let rendered = signal.render(self.sample_rate, length);
+        let envelope = self.fade.envelope(self.sample_rate, length);
+        for (sample, gain) in rendered.into_iter().zip(envelope) {
+            let (left_total, right_total) = apply_gains(gains, sample * gain * amplitude);
+
+            let samples_by_channel = SamplesByChannel::new()
+                .front_left(left_total)
+                .front_right(right_total);
+
+            writer.write_samples(self.sample_ctr, samples_by_channel)?;
+
+            self.sample_ctr += 1;
+        }
+
+        Ok(())
+    }
+
+    fn write_silence(&mut self, writer: &mut WavWriter, length: usize) -> Result<()> {
+        for _ in 0..length {
+            let samples_by_channel = SamplesByChannel::new().front_left(0.0).front_right(0.0);
+
+            writer.write_samples(self.sample_ctr, samples_by_channel)?;
+
+            self.sample_ctr += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a duration in seconds into the nearest number of samples
+pub fn seconds_to_samples(sample_rate: u32, seconds: f32) -> usize {
+    (seconds as f64 * sample_rate as f64).round() as usize
+}
+
+fn manifest_segment(
+    label: String,
+    start: usize,
+    length: usize,
+    matrix: String,
+    signal: Signal,
+    amplitude: f32,
+    tone: Tone,
+) -> ManifestSegment {
+    let position = match tone.placement {
+        Placement::Position(position) => Some(position.name()),
+        Placement::Azimuth(_) => None,
+    };
+
+    ManifestSegment {
+        label,
+        start,
+        length,
+        position,
+        azimuth: Some(tone.placement.azimuth()),
+        matrix,
+        signal,
+        amplitude,
+        left_total: Some(tone.left_total.into()),
+        right_total: Some(tone.right_total.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{analysis::StereoWav, matrix::SqMatrix, writer::read_chunks};
+
+    use super::*;
+
+    // The sample that each cue in the wav points to, in order
+    fn read_cue_samples(path: &Path) -> Vec<usize> {
+        let bytes = fs::read(path).unwrap();
+        let chunks = read_chunks(&bytes, 12);
+        let (_, cue) = chunks.iter().find(|(id, _)| id == "cue ").unwrap();
+        let count = u32::from_le_bytes(cue[0..4].try_into().unwrap()) as usize;
+        (0..count)
+            .map(|index| {
+                let offset = 4 + index * 24 + 20;
+                u32::from_le_bytes(cue[offset..offset + 4].try_into().unwrap()) as usize
+            })
+            .collect()
+    }
+
+    // Each segment starts at its cue, after silence, and ends before the next segment's silence
+    fn assert_segments_match_wav(manifest: &Manifest, path: &Path) {
+        let cue_samples = read_cue_samples(path);
+        let wav = StereoWav::read(path).unwrap();
+
+        assert_eq!(cue_samples.len(), manifest.segments.len());
+        let mut end_of_previous = 0;
+        for (segment, cue_sample) in manifest.segments.iter().zip(cue_samples) {
+            assert_eq!(segment.start, cue_sample, "{}", segment.label);
+
+            for sample_ctr in end_of_previous..segment.start {
+                assert_eq!(wav.left_total[sample_ctr], 0.0, "{}", segment.label);
+                assert_eq!(wav.right_total[sample_ctr], 0.0, "{}", segment.label);
+            }
+
+            let end = segment.start + segment.length;
+            let energy: f32 = (segment.start..end)
+                .map(|sample_ctr| {
+                    wav.left_total[sample_ctr].powi(2) + wav.right_total[sample_ctr].powi(2)
+                })
+                .sum();
+            assert!(energy > 0.0, "{}", segment.label);
+
+            end_of_previous = end;
+        }
+
+        assert!(wav.left_total[end_of_previous..]
+            .iter()
+            .chain(wav.right_total[end_of_previous..].iter())
+            .all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn sequence_manifest_starts_at_the_cues() {
+        let path = std::env::temp_dir().join("soft_matrix_test_tones_sequence_manifest.wav");
+        let mut tone_generator = ToneGenerator::with_durations(44100, 882.0, 0.05, 0.01);
+        tone_generator.set_frequencies(vec![441.0, 882.0], FrequencyMode::Sequential);
+        let sequence = ToneSequence::for_matrix(&SqMatrix);
+
+        tone_generator.write_sequence(&path, &sequence).unwrap();
+        let manifest = tone_generator.manifest(&SqMatrix, &sequence);
+
+        assert_eq!(manifest.segments.len(), sequence.tones.len() * 2);
+        assert_segments_match_wav(&manifest, &path);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn panning_sweep_manifest_starts_at_the_cues() {
+        let path = std::env::temp_dir().join("soft_matrix_test_tones_panning_sweep_manifest.wav");
+        let mut tone_generator = ToneGenerator::with_durations(44100, 882.0, 0.05, 0.01);
+        tone_generator.set_frequencies(vec![441.0, 882.0], FrequencyMode::Sequential);
+
+        tone_generator
+            .write_panning_sweep(&path, &SqMatrix, 0.1)
+            .unwrap();
+        let manifest = tone_generator.panning_sweep_manifest(&SqMatrix, 0.1);
+
+        assert_eq!(manifest.segments.len(), 2);
+        assert_segments_match_wav(&manifest, &path);
+        for segment in manifest.segments.iter() {
+            assert_eq!(segment.length, 4410);
+            assert_eq!(segment.azimuth, None);
+            assert_eq!(segment.left_total, None);
+            assert_eq!(segment.right_total, None);
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+}
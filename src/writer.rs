@@ -0,0 +1,455 @@
+use std::{
+    fs::OpenOptions,
+    io::{Error, ErrorKind, Result, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use wave_stream::{
+    samples_by_channel::SamplesByChannel,
+    wave_header::{Channels, SampleFormat, WavHeader},
+    wave_writer::RandomAccessWavWriter,
+    write_wav_to_file_path,
+};
+
+use crate::random::Random;
+
+// Dither is seeded, so that the same options always write the same file
+const DITHER_SEED: u64 = 1;
+
+/// How samples are written into wav files
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputFormat {
+    /// 16-bit, 24-bit, or 32-bit float. 8-bit is not supported
+    pub sample_format: SampleFormat,
+    /// Adds TPDF (triangular) dither before rounding to an integer format. Float is never dithered
+    pub dither: bool,
+}
+
+impl Default for OutputFormat {
+    fn default() -> OutputFormat {
+        OutputFormat {
+            sample_format: SampleFormat::Float,
+            dither: false,
+        }
+    }
+}
+
+/// A labeled marker at a sample, such as the start of a tone. Cues are written into the wav's
+/// `cue ` and `LIST` `adtl` chunks, so DAWs show them as markers and scripts can find each segment
+/// by name
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cue {
+    pub sample: usize,
+    pub label: String,
+}
+
+enum FormatWriter {
+    Int16(RandomAccessWavWriter<i16>),
+    Int24(RandomAccessWavWriter<i32>),
+    Float(RandomAccessWavWriter<f32>),
+}
+
+/// Writes a wav file in any `OutputFormat`. Samples are always floats, where 1 is full scale: For
+/// integer formats they are scaled, optionally dithered, rounded, and clipped
+pub struct WavWriter {
+    path: PathBuf,
+    writer: FormatWriter,
+    dither: Option<Random>,
+    cues: Vec<Cue>,
+}
+
+impl WavWriter {
+    pub fn create(
+        path: &Path,
+        channels: Channels,
+        sample_rate: u32,
+        output_format: OutputFormat,
+    ) -> Result<WavWriter> {
+        let header = WavHeader {
+            sample_format: output_format.sample_format,
+            channels,
+            sample_rate,
+        };
+        let open_wav = write_wav_to_file_path(path, header)?;
+
+        let writer = match output_format.sample_format {
+            SampleFormat::Int16 => FormatWriter::Int16(open_wav.get_random_access_i16_writer()?),
+            SampleFormat::Int24 => FormatWriter::Int24(open_wav.get_random_access_i24_writer()?),
+            SampleFormat::Float => FormatWriter::Float(open_wav.get_random_access_f32_writer()?),
+            SampleFormat::Int8 => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "8-bit wavs are not supported",
+                ))
+            }
+        };
+
+        let dither = if output_format.dither {
+            Some(Random::new(DITHER_SEED))
+        } else {
+            None
+        };
+
+        Ok(WavWriter {
+            path: path.to_path_buf(),
+            writer,
+            dither,
+            cues: Vec::new(),
+        })
+    }
+
+    pub fn write_samples(
+        &mut self,
+        sample: usize,
+        samples_by_channel: SamplesByChannel<f32>,
+    ) -> Result<()> {
+        match &mut self.writer {
+            FormatWriter::Int16(writer) => {
+                let samples_by_channel = map_samples(samples_by_channel, |value| {
+                    quantize(value, i16::MAX as f32, &mut self.dither) as i16
+                });
+                writer.write_samples(sample, samples_by_channel)
+            }
+            FormatWriter::Int24(writer) => {
+                let samples_by_channel = map_samples(samples_by_channel, |value| {
+                    quantize(value, 8388607.0, &mut self.dither) as i32
+                });
+                writer.write_samples(sample, samples_by_channel)
+            }
+            FormatWriter::Float(writer) => writer.write_samples(sample, samples_by_channel),
+        }
+    }
+
+    /// Adds a labeled cue at a sample, which is written when the file is finished
+    pub fn add_cue(&mut self, sample: usize, label: String) {
+        self.cues.push(Cue { sample, label });
+    }
+
+    /// Finishes writing the file: Writes the sample count, then appends the cues after the samples
+    pub fn finish(self) -> Result<()> {
+        let WavWriter {
+            path,
+            mut writer,
+            cues,
+            ..
+        } = self;
+
+        match &mut writer {
+            FormatWriter::Int16(writer) => writer.flush()?,
+            FormatWriter::Int24(writer) => writer.flush()?,
+            FormatWriter::Float(writer) => writer.flush()?,
+        }
+        drop(writer);
+
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let mut length = file.seek(SeekFrom::End(0))?;
+
+        if !cues.is_empty() {
+            // Chunks start at even offsets
+            if length % 2 == 1 {
+                file.write_all(&[0])?;
+                length += 1;
+            }
+
+            let chunks = cue_chunks(&cues);
+            file.write_all(&chunks)?;
+            length += chunks.len() as u64;
+        }
+
+        // The RIFF chunk's size is set from the file's length, so that it includes the cues and
+        // every chunk in the header
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&((length - 8) as u32).to_le_bytes())?;
+
+        file.flush()
+    }
+}
+
+// A `cue ` chunk with a cue point for each cue, followed by a `LIST` chunk with an `adtl` list that
+// has a `labl` for each cue. Cue points are numbered from 1
+fn cue_chunks(cues: &[Cue]) -> Vec<u8> {
+    let mut cue_chunk = Vec::new();
+    cue_chunk.extend_from_slice(&(cues.len() as u32).to_le_bytes());
+    for (index, cue) in cues.iter().enumerate() {
+        let id = index as u32 + 1;
+        let sample = cue.sample as u32;
+
+        cue_chunk.extend_from_slice(&id.to_le_bytes());
+        // Play order position
+        cue_chunk.extend_from_slice(&sample.to_le_bytes());
+        cue_chunk.extend_from_slice(b"data");
+        // Chunk start and block start are 0 for uncompressed samples in a data chunk
+        cue_chunk.extend_from_slice(&0u32.to_le_bytes());
+        cue_chunk.extend_from_slice(&0u32.to_le_bytes());
+        cue_chunk.extend_from_slice(&sample.to_le_bytes());
+    }
+
+    let mut list_chunk = Vec::new();
+    list_chunk.extend_from_slice(b"adtl");
+    for (index, cue) in cues.iter().enumerate() {
+        let mut labl = Vec::new();
+        labl.extend_from_slice(&(index as u32 + 1).to_le_bytes());
+        labl.extend_from_slice(cue.label.as_bytes());
+        labl.push(0);
+
+        append_chunk(&mut list_chunk, b"labl", &labl);
+    }
+
+    let mut chunks = Vec::new();
+    append_chunk(&mut chunks, b"cue ", &cue_chunk);
+    append_chunk(&mut chunks, b"LIST", &list_chunk);
+    chunks
+}
+
+// Appends a chunk's id, size, and data, padded to an even length
+fn append_chunk(chunks: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    chunks.extend_from_slice(id);
+    chunks.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    chunks.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        chunks.push(0);
+    }
+}
+
+// Scales a sample so that 1 is `full_scale`, then dithers, rounds, and clips it
+fn quantize(value: f32, full_scale: f32, dither: &mut Option<Random>) -> f64 {
+    let mut scaled = value as f64 * full_scale as f64;
+    if let Some(random) = dither {
+        scaled += random.next_triangular();
+    }
+
+    scaled
+        .round()
+        .clamp(-(full_scale as f64) - 1.0, full_scale as f64)
+}
+
+fn map_samples<T>(
+    samples_by_channel: SamplesByChannel<f32>,
+    mut convert: impl FnMut(f32) -> T,
+) -> SamplesByChannel<T> {
+    let mut convert = |value: Option<f32>| value.map(&mut convert);
+
+    SamplesByChannel {
+        front_left: convert(samples_by_channel.front_left),
+        front_right: convert(samples_by_channel.front_right),
+        front_center: convert(samples_by_channel.front_center),
+        low_frequency: convert(samples_by_channel.low_frequency),
+        back_left: convert(samples_by_channel.back_left),
+        back_right: convert(samples_by_channel.back_right),
+        front_left_of_center: convert(samples_by_channel.front_left_of_center),
+        front_right_of_center: convert(samples_by_channel.front_right_of_center),
+        back_center: convert(samples_by_channel.back_center),
+        side_left: convert(samples_by_channel.side_left),
+        side_right: convert(samples_by_channel.side_right),
+        top_center: convert(samples_by_channel.top_center),
+        top_front_left: convert(samples_by_channel.top_front_left),
+        top_front_center: convert(samples_by_channel.top_front_center),
+        top_front_right: convert(samples_by_channel.top_front_right),
+        top_back_left: convert(samples_by_channel.top_back_left),
+        top_back_center: convert(samples_by_channel.top_back_center),
+        top_back_right: convert(samples_by_channel.top_back_right),
+    }
+}
+
+// Each chunk's id and data from `offset` on, which must fill the rest of the bytes exactly
+#[cfg(test)]
+pub(crate) fn read_chunks(bytes: &[u8], mut offset: usize) -> Vec<(String, Vec<u8>)> {
+    let mut chunks = Vec::new();
+    while offset < bytes.len() {
+        let id = String::from_utf8(bytes[offset..offset + 4].to_vec()).unwrap();
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap());
+        let data = bytes[offset + 8..offset + 8 + size as usize].to_vec();
+        chunks.push((id, data));
+        offset += 8 + size as usize + size as usize % 2;
+    }
+    assert_eq!(offset, bytes.len());
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use wave_stream::{open_wav::OpenWav, read_wav_from_file_path};
+
+    use super::*;
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn writes_cues_after_the_samples() {
+        let path = std::env::temp_dir().join("soft_matrix_test_tones_cues.wav");
+
+        // 3 mono 24-bit samples are 9 bytes, so the cues start after a pad byte
+        let output_format = OutputFormat {
+            sample_format: SampleFormat::Int24,
+            dither: false,
+        };
+        let mut writer =
+            WavWriter::create(&path, Channels::new().front_left(), 44100, output_format).unwrap();
+        for sample in 0..3 {
+            writer
+                .write_samples(sample, SamplesByChannel::new().front_left(0.5))
+                .unwrap();
+        }
+        // "center" is an odd-length labl chunk: A 4-byte id, 6 bytes of text, and a NUL
+        writer.add_cue(0, "center".to_string());
+        writer.add_cue(2, "left rear".to_string());
+        writer.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(read_u32(&bytes, 4) as usize, bytes.len() - 8);
+        assert_eq!(&bytes[8..12], b"WAVE");
+
+        let chunks = read_chunks(&bytes, 12);
+        let ids: Vec<&str> = chunks.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["fmt ", "data", "cue ", "LIST"]);
+        assert_eq!(chunks[1].1.len(), 9);
+
+        let cue = &chunks[2].1;
+        assert_eq!(cue.len(), 4 + 2 * 24);
+        assert_eq!(read_u32(cue, 0), 2);
+        for (index, sample) in [0, 2].into_iter().enumerate() {
+            let cue_point = &cue[4 + index * 24..4 + (index + 1) * 24];
+            assert_eq!(read_u32(cue_point, 0), index as u32 + 1);
+            assert_eq!(read_u32(cue_point, 4), sample);
+            assert_eq!(&cue_point[8..12], b"data");
+            assert_eq!(read_u32(cue_point, 12), 0);
+            assert_eq!(read_u32(cue_point, 16), 0);
+            assert_eq!(read_u32(cue_point, 20), sample);
+        }
+
+        let list = &chunks[3].1;
+        assert_eq!(&list[0..4], b"adtl");
+        let labels = read_chunks(list, 4);
+        assert_eq!(
+            labels,
+            [
+                ("labl".to_string(), b"\x01\0\0\0center\0".to_vec()),
+                ("labl".to_string(), b"\x02\0\0\0left rear\0".to_vec()),
+            ]
+        );
+
+        assert_eq!(read_wav_from_file_path(&path).unwrap().len_samples(), 3);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    // Writes mono samples in a format, and returns the data chunk's bytes
+    fn write_data(file_name: &str, output_format: OutputFormat, samples: &[f32]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(file_name);
+        let mut writer =
+            WavWriter::create(&path, Channels::new().front_left(), 44100, output_format).unwrap();
+        for (sample_ctr, sample) in samples.iter().enumerate() {
+            writer
+                .write_samples(sample_ctr, SamplesByChannel::new().front_left(*sample))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        read_chunks(&bytes, 12)
+            .into_iter()
+            .find(|(id, _)| id == "data")
+            .unwrap()
+            .1
+    }
+
+    fn int16_samples(data: &[u8]) -> Vec<i32> {
+        data.chunks(2)
+            .map(|sample| i16::from_le_bytes([sample[0], sample[1]]) as i32)
+            .collect()
+    }
+
+    fn int24_samples(data: &[u8]) -> Vec<i32> {
+        data.chunks(3)
+            .map(|sample| i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) >> 8)
+            .collect()
+    }
+
+    const FULL_SCALE_SAMPLES: [f32; 6] = [1.0, -1.0, 1.5, -1.5, 0.5, 0.0];
+
+    #[test]
+    fn scales_and_clips_int16() {
+        let output_format = OutputFormat {
+            sample_format: SampleFormat::Int16,
+            dither: false,
+        };
+        let data = write_data(
+            "soft_matrix_test_tones_int16.wav",
+            output_format,
+            &FULL_SCALE_SAMPLES,
+        );
+
+        assert_eq!(
+            int16_samples(&data),
+            [32767, -32767, 32767, -32768, 16384, 0]
+        );
+    }
+
+    #[test]
+    fn scales_and_clips_int24() {
+        let output_format = OutputFormat {
+            sample_format: SampleFormat::Int24,
+            dither: false,
+        };
+        let data = write_data(
+            "soft_matrix_test_tones_int24.wav",
+            output_format,
+            &FULL_SCALE_SAMPLES,
+        );
+
+        assert_eq!(
+            int24_samples(&data),
+            [8388607, -8388607, 8388607, -8388608, 4194304, 0]
+        );
+    }
+
+    #[test]
+    fn dither_is_within_one_step_and_reproducible() {
+        let samples: Vec<f32> = (0..1000)
+            .map(|sample| (sample as f32 * 0.01).sin() * 0.5)
+            .collect();
+        let plain = int16_samples(&write_data(
+            "soft_matrix_test_tones_plain.wav",
+            OutputFormat {
+                sample_format: SampleFormat::Int16,
+                dither: false,
+            },
+            &samples,
+        ));
+
+        let dithered_format = OutputFormat {
+            sample_format: SampleFormat::Int16,
+            dither: true,
+        };
+        let dithered = int16_samples(&write_data(
+            "soft_matrix_test_tones_dithered.wav",
+            dithered_format,
+            &samples,
+        ));
+        let dithered_again = int16_samples(&write_data(
+            "soft_matrix_test_tones_dithered_again.wav",
+            dithered_format,
+            &samples,
+        ));
+
+        assert_eq!(dithered, dithered_again);
+        assert_ne!(dithered, plain);
+        for (dithered, plain) in dithered.iter().zip(plain.iter()) {
+            assert!((dithered - plain).abs() <= 1, "{} vs {}", dithered, plain);
+        }
+
+        // Dither doesn't push full scale past the integer's limits
+        let mut random = Some(Random::new(DITHER_SEED));
+        for _ in 0..1000 {
+            let value = quantize(1.0, i16::MAX as f32, &mut random);
+            assert!((i16::MIN as f64..=i16::MAX as f64).contains(&value));
+        }
+    }
+}